@@ -2,11 +2,10 @@ import { getOctokit } from "@actions/github";
 import { exec } from "@actions/exec";
 import { HttpClient } from "@actions/http-client";
 import { exit } from "process";
-import { createWriteStream, promises, readdirSync, readFileSync, writeFileSync } from "fs";
-import * as compressing from "compressing";
+import { createWriteStream, promises } from "fs";
 import crypto from "crypto";
 import sleep from "atomic-sleep";
-import { build } from "./gulpfile.js";
+import { scripts } from "./gulpfile.js";
 import { log } from "console";
 import sodium from "libsodium-wrappers";
 import core from "@actions/core";
@@ -49,27 +48,25 @@ octokit.rest.repos
       writer.on("close", async () => {
         await exec("ls -l");
 
-        log("解压文件");
-        await compressing.zip.uncompress("./bedrock-samples.zip", "./bedrock-samples");
-
         log("处理配方文件");
-        await exec("cargo run -r", [], { cwd: "./mq_decrating_table-rs" });
-
-        for (const file of readdirSync("./pack/mq_decrafting_table_bp/texts").concat(readdirSync("./pack/mq_decrafting_table_rp/texts"))) {
-          if (file.includes(".lang")) {
-            writeFileSync(
-              `./pack/mq_decrafting_table_bp/texts/${file}`,
-              readFileSync(`./pack/mq_decrafting_table_bp/texts/${file}`, { encoding: "utf-8" }).replace("$TAG", latest_release.tag_name)
-            );
-            writeFileSync(
-              `./pack/mq_decrafting_table_rp/texts/${file}`,
-              readFileSync(`./pack/mq_decrafting_table_rp/texts/${file}`, { encoding: "utf-8" }).replace("$TAG", latest_release.tag_name)
-            );
-          }
-        }
-
-        log("打包文件");
-        build().then(() =>
+        await exec("cargo run -r --", ["--samples=../bedrock-samples.zip", `--tag=${latest_release.tag_name}`], {
+          cwd: "./mq_decrating_table-rs",
+        });
+
+        log("构建脚本");
+        await scripts();
+
+        log("打包");
+        exec(
+          "cargo run -r --",
+          [
+            "--no-generate",
+            `--tag=${latest_release.tag_name}`,
+            "--package=../target/mq_decrafting_table.mcaddon",
+            "--scripts=../target/mq_decrafting_table_bp/scripts",
+          ],
+          { cwd: "./mq_decrating_table-rs" }
+        ).then(() =>
           setTimeout(async () => {
             log("上传 Release 文件");
             const file = await promises.readFile("./target/mq_decrafting_table.mcaddon");
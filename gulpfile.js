@@ -53,12 +53,16 @@ async function main() {
 	}
 }
 
-export async function build() {
+export async function scripts() {
 	await deleteAsync('target', { force: true });
 	config.outDir = `target/${bpfoldername}/scripts`;
 	config.minify = true;
 	config.treeshake = true;
 	await tsbuild(config);
+}
+
+export async function build() {
+	await scripts();
 	gulp.series(
 		() =>
 			gulp
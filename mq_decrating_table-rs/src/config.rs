@@ -0,0 +1,187 @@
+use crate::{
+    loot_table::tag_items,
+    recipe::{RecipeComponent, Station},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub behavior_pack: PathBuf,
+    pub resource_pack: PathBuf,
+    pub script_manifest: PathBuf,
+    pub output_manifest: PathBuf,
+    pub recipe_namespace: String,
+    pub item_namespace: String,
+    pub crafting_tag: String,
+    pub display_name: Option<String>,
+    pub format_version: Option<String>,
+    pub item_format_version: Option<String>,
+    pub unlock: Unlock,
+    pub only_crafting_table: bool,
+    pub include: Filter,
+    pub exclude: Filter,
+}
+
+/// 分解配方在配方书中的解锁方式
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unlock {
+    /// 始终解锁
+    #[default]
+    Always,
+    /// 获得被分解的物品时解锁
+    Result,
+    /// 沿用正向配方的解锁条件, 没有时按被分解的物品解锁
+    Inherit,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filter {
+    pub identifiers: Vec<String>,
+    pub tags: Vec<String>,
+    pub results: Vec<String>,
+    pub ingredients: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            behavior_pack: "../pack/mq_decrafting_table_bp".into(),
+            resource_pack: "../pack/mq_decrafting_table_rp".into(),
+            script_manifest: "../src/decrafting.ts".into(),
+            output_manifest: "output_manifest.json".into(),
+            recipe_namespace: "mq_decrafting_table".to_owned(),
+            item_namespace: "mq_decrafting_item".to_owned(),
+            crafting_tag: "mq_decrafting_table".to_owned(),
+            display_name: None,
+            format_version: None,
+            item_format_version: None,
+            unlock: Unlock::default(),
+            only_crafting_table: false,
+            include: Filter::default(),
+            exclude: Filter::default(),
+        }
+    }
+}
+
+fn glob(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ti));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == t[ti] => {
+                pi += 1;
+                ti += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    pi = sp + 1;
+                    ti = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+fn any_glob(patterns: &[String], text: &str) -> bool {
+    patterns.iter().any(|p| glob(p, text))
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        self.identifiers.is_empty()
+            && self.tags.is_empty()
+            && self.results.is_empty()
+            && self.ingredients.is_empty()
+    }
+
+    fn matches(&self, component: &RecipeComponent) -> bool {
+        any_glob(&self.identifiers, &component.description.identifier)
+            || component
+                .tags
+                .iter()
+                .any(|t| self.tags.iter().any(|p| p == t))
+            || component.result_items().any(|i| any_glob(&self.results, i))
+            || component.ingredient_items().any(|i| {
+                any_glob(&self.ingredients, i)
+                    || tag_items(i)
+                        .is_some_and(|items| items.iter().any(|i| any_glob(&self.ingredients, i)))
+            })
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path).context("无法读取配置文件")?)
+            .context("解析配置文件失败")
+    }
+
+    pub fn accepts(&self, component: &RecipeComponent) -> bool {
+        (!self.only_crafting_table || component.station() == Some(Station::CraftingTable))
+            && (self.include.is_empty() || self.include.matches(component))
+            && !self.exclude.matches(component)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::Recipe;
+
+    const STICK: &str = r##"{
+        "format_version": "1.20.10",
+        "minecraft:recipe_shaped": {
+            "description": { "identifier": "minecraft:stick" },
+            "tags": ["crafting_table"],
+            "pattern": ["#", "#"],
+            "key": { "#": { "tag": "minecraft:planks" } },
+            "result": { "item": "minecraft:stick", "count": 4 }
+        }
+    }"##;
+
+    fn filter(json: &str) -> Filter {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn glob_backtracks() {
+        assert!(glob("minecraft:*_planks", "minecraft:dark_oak_planks"));
+        assert!(glob("*a*b", "xaxxab"));
+        assert!(glob("*:?ak_*", "minecraft:oak_log"));
+        assert!(glob("a*", "a"));
+        assert!(!glob("*a*b", "xaxxa"));
+        assert!(!glob("?", ""));
+        assert!(!glob("minecraft:?_planks", "minecraft:oak_planks"));
+    }
+
+    #[test]
+    fn accepts_tag_members_and_prefers_exclude() {
+        let source: Recipe = serde_json::from_str(STICK).unwrap();
+        let component = source.component.unwrap();
+        let mut config = Config {
+            include: filter(r#"{ "ingredients": ["minecraft:oak_*"] }"#),
+            ..Config::default()
+        };
+        assert!(config.accepts(&component));
+        config.include = filter(r#"{ "ingredients": ["minecraft:iron_*"] }"#);
+        assert!(!config.accepts(&component));
+        config.include = filter(r#"{ "identifiers": ["minecraft:*"] }"#);
+        config.exclude = filter(r#"{ "results": ["*:stick"] }"#);
+        assert!(!config.accepts(&component));
+    }
+}
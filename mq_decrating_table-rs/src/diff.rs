@@ -0,0 +1,88 @@
+use std::{collections::BTreeMap, fmt, path::Path};
+
+const SECTIONS: [(&str, &str); 2] = [
+    ("recipes/decrafting", "分解配方"),
+    ("loot_tables/decrafting", "战利品表"),
+];
+
+#[derive(Default)]
+struct Section {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+impl Section {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub struct Diff {
+    sections: Vec<(&'static str, Section)>,
+}
+
+fn name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map_or_else(|| path.to_owned(), |s| s.to_string_lossy().into_owned())
+}
+
+impl Diff {
+    pub fn new(
+        behavior_pack: &Path,
+        old: &BTreeMap<String, Vec<u8>>,
+        new: &BTreeMap<String, Vec<u8>>,
+    ) -> Self {
+        let sections = SECTIONS
+            .into_iter()
+            .map(|(dir, title)| {
+                let dir = behavior_pack.join(dir);
+                let in_dir = |path: &&String| Path::new(path.as_str()).parent() == Some(&dir);
+                let mut section = Section::default();
+                for (path, contents) in new.iter().filter(|(p, _)| in_dir(p)) {
+                    match old.get(path) {
+                        None => section.added.push(name(path)),
+                        Some(previous) if previous != contents => section.changed.push(name(path)),
+                        Some(_) => {}
+                    }
+                }
+                section.removed = old
+                    .keys()
+                    .filter(in_dir)
+                    .filter(|p| !new.contains_key(*p))
+                    .map(|p| name(p))
+                    .collect();
+                (title, section)
+            })
+            .collect();
+        Self { sections }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (title, section) in &self.sections {
+            writeln!(f, "## {title}")?;
+            if section.is_empty() {
+                writeln!(f, "\n无变化\n")?;
+                continue;
+            }
+            for (label, names) in [
+                ("新增", &section.added),
+                ("删除", &section.removed),
+                ("修改", &section.changed),
+            ] {
+                if names.is_empty() {
+                    continue;
+                }
+                writeln!(f, "\n### {label} ({})\n", names.len())?;
+                for name in names {
+                    writeln!(f, "- `{name}`")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
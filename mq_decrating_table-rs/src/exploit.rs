@@ -0,0 +1,535 @@
+use crate::{loot_table::tag_items, recipe::ANY_DATA};
+use rustc_hash::FxHashMap;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Material {
+    Item(String),
+    Tag(String),
+    AnyOf(Vec<String>),
+    /// 战利品表随机给出其中之一, 与玩家放入的 [`Material::AnyOf`] 不能互相抵消
+    Random(Vec<String>),
+}
+
+impl Material {
+    pub fn item(item: &str, data: Option<u16>) -> Self {
+        match data {
+            Some(data) if data != 0 && data != ANY_DATA => Self::Item(format!("{item}:{data}")),
+            _ => Self::Item(item.to_owned()),
+        }
+    }
+
+    pub fn tag(tag: &str) -> Self {
+        match tag_items(tag) {
+            Some(items) => Self::any_of(items.iter().copied()),
+            None => Self::Tag(tag.to_owned()),
+        }
+    }
+
+    pub fn any_of<'a>(items: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut items: Vec<String> = items.into_iter().map(str::to_owned).collect();
+        items.sort_unstable();
+        items.dedup();
+        if items.len() == 1 {
+            Self::Item(items.pop().unwrap())
+        } else {
+            Self::AnyOf(items)
+        }
+    }
+
+    pub fn random<'a>(items: impl IntoIterator<Item = &'a str>) -> Self {
+        match Self::any_of(items) {
+            Self::AnyOf(items) => Self::Random(items),
+            material => material,
+        }
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Material::Item(item) => write!(f, "{item}"),
+            Material::Tag(tag) => write!(f, "#{tag}"),
+            Material::AnyOf(items) => write!(f, "{} 等 {} 种之一", items[0], items.len()),
+            Material::Random(items) => write!(f, "随机 {} 等 {} 种之一", items[0], items.len()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Stack {
+    pub material: Material,
+    pub count: u32,
+}
+
+impl Stack {
+    pub const fn new(material: Material, count: u32) -> Self {
+        Self { material, count }
+    }
+}
+
+impl fmt::Display for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ×{}", self.material, self.count)
+    }
+}
+
+pub struct Flow {
+    pub id: String,
+    pub inputs: Vec<Stack>,
+    pub outputs: Vec<Stack>,
+}
+
+pub enum ExploitKind {
+    Surplus,
+    Conversion,
+}
+
+pub struct Exploit {
+    pub kind: ExploitKind,
+    pub forward: Vec<(String, i64)>,
+    pub inverse: (String, i64),
+    pub gained: Vec<Stack>,
+    pub lost: Vec<Stack>,
+}
+
+fn join(stacks: &[Stack]) -> String {
+    stacks
+        .iter()
+        .map(Stack::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Exploit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] 合成 {} 后分解 {} ×{}: 获得 {}",
+            match self.kind {
+                ExploitKind::Surplus => "净产出",
+                ExploitKind::Conversion => "材料转换",
+            },
+            self.forward
+                .iter()
+                .map(|(id, times)| format!("{id} ×{times}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.inverse.0,
+            self.inverse.1,
+            join(&self.gained)
+        )?;
+        if !self.lost.is_empty() {
+            write!(f, ", 失去 {}", join(&self.lost))?;
+        }
+        Ok(())
+    }
+}
+
+pub const fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+const fn gcd_i64(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd_i64(b, a % b)
+    }
+}
+
+/// 一条链上最多补齐缺口的次数, 限制搜索深度
+const MAX_STEPS: usize = 4;
+
+/// 一个配方执行一次时各材料的净变化
+struct Delta<'a> {
+    id: &'a str,
+    changes: Vec<(Material, i64)>,
+}
+
+impl<'a> Delta<'a> {
+    fn new(id: &'a str, inputs: &[Stack], outputs: &[Stack]) -> Self {
+        let mut changes: BTreeMap<Material, i64> = BTreeMap::new();
+        for s in inputs {
+            *changes.entry(s.material.clone()).or_default() -= i64::from(s.count);
+        }
+        for s in outputs {
+            *changes.entry(s.material.clone()).or_default() += i64::from(s.count);
+        }
+        changes.retain(|_, c| *c != 0);
+        Self {
+            id,
+            changes: changes.into_iter().collect(),
+        }
+    }
+
+    fn get(&self, material: &Material) -> i64 {
+        self.changes
+            .iter()
+            .find(|(m, _)| m == material)
+            .map_or(0, |(_, c)| *c)
+    }
+}
+
+/// 若干正向配方的产物经一个分解配方分解后的净收支
+#[derive(Clone)]
+struct Chain<'a> {
+    net: BTreeMap<&'a Material, i64>,
+    forward: Vec<(&'a str, i64)>,
+    inverse: (&'a str, i64),
+    steps: usize,
+}
+
+impl<'a> Chain<'a> {
+    fn new(inverse: &'a Delta<'a>) -> Self {
+        Self {
+            net: inverse.changes.iter().map(|(m, c)| (m, *c)).collect(),
+            forward: vec![],
+            inverse: (inverse.id, 1),
+            steps: 0,
+        }
+    }
+
+    /// 按最小公倍数放大整条链并加入正向配方, 恰好补齐 `material` 的缺口, 溢出时返回 `None`
+    fn produce(&self, material: &Material, delta: &'a Delta<'a>) -> Option<Self> {
+        let deficit = self.net.get(material)?.checked_neg()?;
+        let produced = delta.get(material);
+        if deficit <= 0 || produced <= 0 {
+            return None;
+        }
+        let gcd = gcd_i64(deficit, produced);
+        let (scale, times) = (produced / gcd, deficit / gcd);
+        let mut chain = self.clone();
+        for count in chain.net.values_mut() {
+            *count = count.checked_mul(scale)?;
+        }
+        for (_, count) in &mut chain.forward {
+            *count = count.checked_mul(scale)?;
+        }
+        chain.inverse.1 = chain.inverse.1.checked_mul(scale)?;
+        for (m, c) in &delta.changes {
+            let net = chain.net.entry(m).or_default();
+            *net = net.checked_add(c.checked_mul(times)?)?;
+        }
+        chain.net.retain(|_, c| *c != 0);
+        match chain.forward.iter_mut().find(|(id, _)| *id == delta.id) {
+            Some((_, count)) => *count = count.checked_add(times)?,
+            None => chain.forward.push((delta.id, times)),
+        }
+        chain.steps += 1;
+        Some(chain)
+    }
+
+    fn split(&self, positive: bool) -> Vec<Stack> {
+        self.net
+            .iter()
+            .filter(|(_, v)| (**v > 0) == positive)
+            .map(|(m, v)| {
+                Stack::new(
+                    (*m).clone(),
+                    u32::try_from(v.unsigned_abs()).unwrap_or(u32::MAX),
+                )
+            })
+            .collect()
+    }
+
+    fn is_surplus(&self) -> bool {
+        self.net.values().all(|c| *c >= 0)
+    }
+
+    fn exploit(&self) -> Option<Exploit> {
+        let (gained, lost) = (self.split(true), self.split(false));
+        if gained.is_empty() {
+            return None;
+        }
+        Some(Exploit {
+            kind: if lost.is_empty() {
+                ExploitKind::Surplus
+            } else {
+                ExploitKind::Conversion
+            },
+            forward: self
+                .forward
+                .iter()
+                .map(|(id, times)| ((*id).to_owned(), *times))
+                .collect(),
+            inverse: (self.inverse.0.to_owned(), self.inverse.1),
+            gained,
+            lost,
+        })
+    }
+}
+
+struct Search<'a> {
+    producers: FxHashMap<&'a Material, Vec<&'a Delta<'a>>>,
+}
+
+impl<'a> Search<'a> {
+    /// 依次用正向配方合成分解配方消耗的每种材料
+    fn craft(&self, inputs: &[&Material], chain: Chain<'a>, found: &mut Vec<Chain<'a>>) {
+        let Some((material, rest)) = inputs.split_first() else {
+            if !chain.forward.is_empty() {
+                found.extend(self.close(chain));
+            }
+            return;
+        };
+        match self.producers.get(material) {
+            Some(producers)
+                if chain.steps < MAX_STEPS && chain.net.get(material).is_some_and(|c| *c < 0) =>
+            {
+                for delta in producers {
+                    if let Some(next) = chain.produce(material, delta) {
+                        self.craft(rest, next, found);
+                    }
+                }
+            }
+            _ => self.craft(rest, chain, found),
+        }
+    }
+
+    /// 尝试用获得的材料重新合成失去的材料, 闭合成净产出的循环
+    fn close(&self, chain: Chain<'a>) -> Option<Chain<'a>> {
+        if chain.net.values().all(|c| *c <= 0) {
+            return None;
+        }
+        if chain.is_surplus() || chain.steps >= MAX_STEPS {
+            return Some(chain);
+        }
+        for (material, _) in chain.net.iter().filter(|(_, c)| **c < 0) {
+            for delta in self.producers.get(material).into_iter().flatten() {
+                let uses_gained = delta
+                    .changes
+                    .iter()
+                    .all(|(m, c)| *c > 0 || chain.net.get(m).is_some_and(|n| *n > 0));
+                if let Some(closed) = uses_gained
+                    .then(|| chain.produce(material, delta))
+                    .flatten()
+                    .and_then(|next| self.close(next))
+                    .filter(Chain::is_surplus)
+                {
+                    return Some(closed);
+                }
+            }
+        }
+        Some(chain)
+    }
+}
+
+#[derive(Default)]
+pub struct Analyzer {
+    forward: Vec<Flow>,
+    inverse: Vec<Flow>,
+    loot: FxHashMap<String, Vec<Stack>>,
+}
+
+impl Analyzer {
+    pub fn add_forward(&mut self, flow: Flow) {
+        self.forward.push(flow);
+    }
+
+    pub fn add_inverse(&mut self, flow: Flow) {
+        self.inverse.push(flow);
+    }
+
+    pub fn add_loot(&mut self, item: String, outputs: Vec<Stack>) {
+        self.loot.insert(item, outputs);
+    }
+
+    fn resolve(&self, outputs: &[Stack]) -> Option<Vec<Stack>> {
+        let mut resolved = vec![];
+        for stack in outputs {
+            match &stack.material {
+                Material::Item(item) if self.loot.contains_key(item) => {
+                    for s in &self.loot[item] {
+                        resolved.push(Stack::new(
+                            s.material.clone(),
+                            s.count.checked_mul(stack.count)?,
+                        ));
+                    }
+                }
+                _ => resolved.push(stack.clone()),
+            }
+        }
+        Some(resolved)
+    }
+
+    /// 从每个分解配方出发, 向前串联产出其原料的正向配方, 报告收支为正的循环
+    pub fn run(&self) -> Vec<Exploit> {
+        let forward: Vec<Delta> = self
+            .forward
+            .iter()
+            .map(|f| Delta::new(&f.id, &f.inputs, &f.outputs))
+            .collect();
+        let mut search = Search {
+            producers: FxHashMap::default(),
+        };
+        for delta in &forward {
+            for (material, count) in &delta.changes {
+                if *count > 0 {
+                    search.producers.entry(material).or_default().push(delta);
+                }
+            }
+        }
+
+        let mut exploits = vec![];
+        for inverse in &self.inverse {
+            let Some(outputs) = self.resolve(&inverse.outputs) else {
+                continue;
+            };
+            let delta = Delta::new(&inverse.id, &inverse.inputs, &outputs);
+            let inputs: Vec<&Material> = delta
+                .changes
+                .iter()
+                .filter(|(_, c)| *c < 0)
+                .map(|(m, _)| m)
+                .collect();
+            let mut found = vec![];
+            search.craft(&inputs, Chain::new(&delta), &mut found);
+            exploits.extend(found.iter().filter_map(Chain::exploit));
+        }
+        exploits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow(id: &str, inputs: &[(&str, u32)], outputs: &[(&str, u32)]) -> Flow {
+        let stacks = |stacks: &[(&str, u32)]| {
+            stacks
+                .iter()
+                .map(|&(item, count)| Stack::new(Material::item(item, None), count))
+                .collect()
+        };
+        Flow {
+            id: id.to_owned(),
+            inputs: stacks(inputs),
+            outputs: stacks(outputs),
+        }
+    }
+
+    fn run(forward: Vec<Flow>, inverse: Flow, loot: &[(&str, u32)]) -> Vec<String> {
+        let mut analyzer = Analyzer::default();
+        for flow in forward {
+            analyzer.add_forward(flow);
+        }
+        analyzer.add_inverse(inverse);
+        analyzer.add_loot("test:placeholder".to_owned(), flow("", &[], loot).outputs);
+        analyzer.run().iter().map(Exploit::to_string).collect()
+    }
+
+    #[test]
+    fn balanced_round_trip_is_not_reported() {
+        let exploits = run(
+            vec![flow("x:stick", &[("x:planks", 2)], &[("x:stick", 4)])],
+            flow("d:stick", &[("x:stick", 4)], &[("test:placeholder", 1)]),
+            &[("x:planks", 2)],
+        );
+        assert!(exploits.is_empty(), "{exploits:?}");
+    }
+
+    #[test]
+    fn surplus() {
+        let exploits = run(
+            vec![flow("x:stick", &[("x:planks", 2)], &[("x:stick", 4)])],
+            flow("d:stick", &[("x:stick", 1)], &[("test:placeholder", 1)]),
+            &[("x:planks", 2)],
+        );
+        assert_eq!(
+            exploits,
+            ["[净产出] 合成 x:stick ×1 后分解 d:stick ×4: 获得 x:planks ×6"]
+        );
+    }
+
+    #[test]
+    fn conversion() {
+        let exploits = run(
+            vec![flow("x:slab", &[("x:oak", 3)], &[("x:slab", 6)])],
+            flow("d:slab", &[("x:slab", 6)], &[("x:birch", 3)]),
+            &[],
+        );
+        assert_eq!(
+            exploits,
+            ["[材料转换] 合成 x:slab ×1 后分解 d:slab ×1: 获得 x:birch ×3, 失去 x:oak ×3"]
+        );
+    }
+
+    #[test]
+    fn random_loot_is_a_conversion() {
+        let planks = || Stack::new(Material::tag("minecraft:planks"), 8);
+        let mut analyzer = Analyzer::default();
+        analyzer.add_forward(Flow {
+            id: "minecraft:chest".to_owned(),
+            inputs: vec![planks()],
+            outputs: vec![Stack::new(Material::item("minecraft:chest", None), 1)],
+        });
+        analyzer.add_inverse(flow(
+            "d:chest",
+            &[("minecraft:chest", 1)],
+            &[("test:placeholder", 1)],
+        ));
+        let items = tag_items("minecraft:planks").unwrap();
+        analyzer.add_loot(
+            "test:placeholder".to_owned(),
+            vec![Stack::new(Material::random(items.iter().copied()), 8)],
+        );
+        let exploits: Vec<_> = analyzer.run().iter().map(Exploit::to_string).collect();
+        let first = items.iter().min().unwrap();
+        let n = items.len();
+        assert_eq!(
+            exploits,
+            [format!(
+                "[材料转换] 合成 minecraft:chest ×1 后分解 d:chest ×1: \
+                 获得 随机 {first} 等 {n} 种之一 ×8, 失去 {first} 等 {n} 种之一 ×8"
+            )]
+        );
+    }
+
+    #[test]
+    fn chain_closes_into_surplus() {
+        let exploits = run(
+            vec![
+                flow("x:planks", &[("x:log", 1)], &[("x:planks", 4)]),
+                flow("x:table", &[("x:planks", 4)], &[("x:table", 1)]),
+            ],
+            flow("d:table", &[("x:table", 1)], &[("x:log", 2)]),
+            &[],
+        );
+        assert_eq!(
+            exploits,
+            ["[净产出] 合成 x:table ×1, x:planks ×1 后分解 d:table ×1: 获得 x:log ×1"]
+        );
+    }
+
+    #[test]
+    fn multiple_inputs() {
+        let exploits = run(
+            vec![flow("x:split", &[("x:a", 1)], &[("x:b", 1), ("x:c", 1)])],
+            flow("d:split", &[("x:b", 1), ("x:c", 1)], &[("x:a", 2)]),
+            &[],
+        );
+        assert_eq!(
+            exploits,
+            ["[净产出] 合成 x:split ×1 后分解 d:split ×1: 获得 x:a ×1"]
+        );
+    }
+
+    #[test]
+    fn overflow_is_skipped() {
+        let exploits = run(
+            vec![flow("x:big", &[("x:a", 1)], &[("x:b", u32::MAX)])],
+            flow(
+                "d:big",
+                &[("x:b", u32::MAX - 1)],
+                &[("test:placeholder", 1)],
+            ),
+            &[("x:a", u32::MAX)],
+        );
+        assert!(exploits.is_empty(), "{exploits:?}");
+    }
+}
@@ -0,0 +1,162 @@
+//! 随机生成配方 JSON, 断言反序列化与分解只返回 `Ok`/`Err` 而不会 panic
+use crate::{
+    config::Unlock,
+    recipe::{Recipe, Target},
+};
+use proptest::{collection::vec, option, prelude::*, sample::select};
+use serde_json::{json, Map, Value};
+
+const ITEMS: [&str; 5] = [
+    "minecraft:stick",
+    "minecraft:bucket",
+    "minecraft:oak_planks",
+    "addon:gem",
+    "",
+];
+const TAGS: [&str; 3] = ["minecraft:planks", "minecraft:logs", "addon:unknown"];
+const RECIPE_TAGS: [&str; 4] = ["crafting_table", "stonecutter", "deprecated", "furnace"];
+
+fn item() -> impl Strategy<Value = Value> {
+    (
+        select(&ITEMS[..]),
+        option::of(-2..40000i32),
+        option::of(prop_oneof![0..100u32, any::<u32>()]),
+        any::<bool>(),
+    )
+        .prop_map(|(item, data, count, shorthand)| {
+            if shorthand && count.is_none() {
+                return match data {
+                    Some(data) => format!("{item}:{data}").into(),
+                    None => item.into(),
+                };
+            }
+            let mut stack = json!({ "item": item });
+            if let Some(data) = data {
+                stack["data"] = data.into();
+            }
+            if let Some(count) = count {
+                stack["count"] = count.into();
+            }
+            stack
+        })
+}
+
+fn ingredient() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        3 => item(),
+        1 => select(&TAGS[..]).prop_map(|tag| json!({ "tag": tag })),
+    ]
+}
+
+fn result() -> impl Strategy<Value = Value> {
+    prop_oneof![item(), vec(item(), 0..12).prop_map(Value::Array)]
+}
+
+fn shaped() -> impl Strategy<Value = Value> {
+    (
+        vec("[#AB X]{0,4}", 0..5),
+        vec((select(&['#', 'A', 'B', 'X', ' '][..]), ingredient()), 0..5),
+        result(),
+    )
+        .prop_map(|(pattern, keys, result)| {
+            let key: Map<String, Value> =
+                keys.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+            json!({ "pattern": pattern, "key": key, "result": result })
+        })
+}
+
+fn shapeless() -> impl Strategy<Value = Value> {
+    (vec(ingredient(), 0..12), item())
+        .prop_map(|(ingredients, result)| json!({ "ingredients": ingredients, "result": result }))
+}
+
+fn recipe() -> impl Strategy<Value = Value> {
+    (
+        prop_oneof![
+            shaped().prop_map(|c| ("minecraft:recipe_shaped", c)),
+            shapeless().prop_map(|c| ("minecraft:recipe_shapeless", c)),
+        ],
+        vec(select(&RECIPE_TAGS[..]), 0..3),
+        "[a-z:_]{0,12}",
+    )
+        .prop_map(|((kind, mut component), tags, identifier)| {
+            component["tags"] = tags.into();
+            component["description"] = json!({ "identifier": identifier });
+            json!({ "format_version": "1.20.10", kind: component })
+        })
+}
+
+fn invert(s: &str) {
+    let Ok(source) = serde_json::from_str::<Recipe>(s) else {
+        return;
+    };
+    let Some(component) = source.component else {
+        return;
+    };
+    component.flow();
+    component.placeholder_name();
+    let Ok((recipe, loot_table)) = component.inverse(Target {
+        recipe_id: "fuzz:recipe",
+        item_id: "fuzz:item",
+        crafting_tag: "fuzz",
+        unlock: Unlock::Inherit,
+    }) else {
+        return;
+    };
+    if let Some(recipe) = recipe {
+        let _ = serde_json::to_string(&recipe);
+    }
+    if let Some(loot_table) = loot_table {
+        loot_table.outputs();
+        let _ = serde_json::to_string(&loot_table);
+    }
+}
+
+fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        "[a-z#: ]{0,6}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..6).prop_map(Value::Array),
+            vec(
+                (
+                    select(
+                        &[
+                            "minecraft:recipe_shaped",
+                            "minecraft:recipe_shapeless",
+                            "description",
+                            "identifier",
+                            "pattern",
+                            "key",
+                            "result",
+                            "ingredients",
+                            "item",
+                            "tag",
+                            "count",
+                            "tags",
+                        ][..]
+                    ),
+                    inner
+                ),
+                0..6
+            )
+            .prop_map(|e| Value::Object(e.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn inverse_never_panics(recipe in recipe()) {
+        invert(&recipe.to_string());
+    }
+
+    #[test]
+    fn deserialize_never_panics(value in json()) {
+        invert(&value.to_string());
+    }
+}
@@ -0,0 +1,109 @@
+//! 分解配方的黄金文件测试, 设置 `UPDATE_GOLDENS=1` 以在有意修改后重新生成预期输出
+use crate::{
+    config::Config,
+    output::to_pretty,
+    recipe::{local_name, Recipe, Target},
+    version,
+};
+use serde_json::{json, Value};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+pub fn fixtures() -> Vec<(String, String)> {
+    let mut fixtures: Vec<_> = fs::read_dir(dir("recipes"))
+        .unwrap()
+        .map(|e| {
+            let path = e.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(path).unwrap())
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+fn invert(name: &str, s: &str) -> Value {
+    let source: Recipe = match serde_json::from_str(s) {
+        Ok(source) => source,
+        Err(e) => return json!({ "error": e.to_string() }),
+    };
+    let Some(component) = source.component else {
+        return json!({ "skipped": "其他配方" });
+    };
+    if name.contains("_from_") {
+        return json!({ "skipped": "_from_" });
+    }
+    if component.is_deprecated() {
+        return json!({ "skipped": "deprecated" });
+    }
+    let config = Config::default();
+    let recipe_id = format!(
+        "{}:{}",
+        config.recipe_namespace,
+        local_name(&component.description.identifier)
+    );
+    let item_id = format!("{}:{}", config.item_namespace, component.placeholder_name());
+    let target = Target {
+        recipe_id: &recipe_id,
+        item_id: &item_id,
+        crafting_tag: &config.crafting_tag,
+        unlock: config.unlock,
+    };
+    match component.inverse(target) {
+        Ok((recipe, loot_table)) => {
+            let recipe = recipe.map(|recipe| {
+                let mut value = serde_json::to_value(&recipe).unwrap();
+                if let Some(component) = &recipe.component {
+                    value["format_version"] =
+                        version::resolve(source.format_version, &component.features(), None)
+                            .unwrap()
+                            .into();
+                }
+                value
+            });
+            json!({ "recipe": recipe, "loot_table": loot_table })
+        }
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
+
+#[test]
+fn goldens() {
+    let update = env::var_os("UPDATE_GOLDENS").is_some();
+    let expected_dir = dir("expected");
+    let fixtures = fixtures();
+    let mut mismatched = vec![];
+    for (name, s) in &fixtures {
+        let actual = String::from_utf8(to_pretty(&invert(name, s)).unwrap()).unwrap() + "\n";
+        let path = expected_dir.join(format!("{name}.json"));
+        if update {
+            fs::create_dir_all(&expected_dir).unwrap();
+            fs::write(&path, actual).unwrap();
+        } else if fs::read_to_string(&path).ok().as_deref() != Some(actual.as_str()) {
+            mismatched.push(name.clone());
+        }
+    }
+    for entry in fs::read_dir(&expected_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_string_lossy();
+        if !fixtures.iter().any(|(n, _)| *n == name) {
+            if update {
+                fs::remove_file(&path).unwrap();
+            } else {
+                mismatched.push(format!("{name} (没有对应的配方)"));
+            }
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "黄金文件不一致, 确认修改后使用 UPDATE_GOLDENS=1 重新生成: {mismatched:?}"
+    );
+}
@@ -1,15 +1,19 @@
-use crate::recipe::{Ingredient, ItemStack, ItemTag, Key, Shaped};
-use anyhow::{bail, Result};
+use crate::{
+    exploit::{Material, Stack},
+    recipe::{Ingredient, ItemStack, ItemTag, Key, Shaped},
+    stack_size::{max_stack_size, DEFAULT_STACK_SIZE},
+};
+use anyhow::{Context, Result};
 use serde::Serialize;
 
 #[derive(Serialize)]
 struct SetCount<'a> {
     function: &'a str,
-    count: u8,
+    count: u32,
 }
 
 impl<'a> SetCount<'a> {
-    const fn new(count: u8) -> Self {
+    const fn new(count: u32) -> Self {
         Self {
             function: "set_count",
             count,
@@ -20,11 +24,11 @@ impl<'a> SetCount<'a> {
 #[derive(Serialize)]
 struct SetData<'a> {
     function: &'a str,
-    data: u8,
+    data: u16,
 }
 
 impl<'a> SetData<'a> {
-    const fn new(data: u8) -> Self {
+    const fn new(data: u16) -> Self {
         Self {
             function: "set_data",
             data,
@@ -47,35 +51,53 @@ struct Entry<'a> {
     ty: &'a str,
     weight: u8,
     name: &'a str,
-    functions: [Function<'a>; 2],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    functions: Vec<Function<'a>>,
 }
 
 impl<'a> Entry<'a> {
-    const fn new(name: &'a str, count: u8, data: u8) -> Self {
+    /// 只写入非默认的函数; 数据值为 0 时不写 set_data, 以免覆盖按状态区分的新版物品
+    fn new(name: &'a str, count: u32, data: Option<u16>) -> Self {
+        let mut functions = vec![];
+        if count != 1 {
+            functions.push(Function::SetCount(SetCount::new(count)));
+        }
+        if let Some(data) = data.filter(|&d| d != 0) {
+            functions.push(Function::SetData(SetData::new(data)));
+        }
         Self {
             ty: "item",
             weight: 1,
             name,
-            functions: [
-                Function::SetCount(SetCount::new(count)),
-                Function::SetData(SetData::new(data)),
-            ],
+            functions,
         }
     }
 
-    const fn from_item_stack(item_stack: ItemStack<'a>) -> Self {
+    fn from_item_stack(item_stack: ItemStack<'a>) -> Self {
+        let item_stack = item_stack.concrete();
         Self::new(
             item_stack.item,
-            match item_stack.count {
-                Some(c) => c,
-                None => 1,
-            },
-            match item_stack.data {
-                Some(d) => d,
-                None => 0,
-            },
+            item_stack.count.unwrap_or(1),
+            item_stack.data,
         )
     }
+
+    fn count(&self) -> u32 {
+        self.functions
+            .iter()
+            .find_map(|f| match f {
+                Function::SetCount(c) => Some(c.count),
+                _ => None,
+            })
+            .unwrap_or(1)
+    }
+
+    fn data(&self) -> Option<u16> {
+        self.functions.iter().find_map(|f| match f {
+            Function::SetData(d) => Some(d.data),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Serialize)]
@@ -84,150 +106,197 @@ pub struct Pool<'a> {
     entries: Vec<Entry<'a>>,
 }
 
-macro_rules! build_entries {
-    ($c:expr, $($elem:expr),+ $(,)?) => {{
-        [$(
-            Entry::new($elem, $c, 0)
-        ),+]
-    }};
-}
-
 impl<'a> Pool<'a> {
     const fn new(entries: Vec<Entry<'a>>) -> Self {
         Self { rolls: 1, entries }
     }
 
-    fn from_item_tag(value: &ItemTag<'a>, count: u8) -> Result<Self> {
-        Ok(Self {
-            rolls: 1,
-            entries: (match value.tag {
-                "minecraft:planks" => build_entries!(
-                    count,
-                    "minecraft:oak_planks",
-                    "minecraft:spruce_planks",
-                    "minecraft:birch_planks",
-                    "minecraft:jungle_planks",
-                    "minecraft:acacia_planks",
-                    "minecraft:dark_oak_planks",
-                    "minecraft:mangrove_planks",
-                    "minecraft:cherry_planks",
-                    "minecraft:bamboo_planks",
-                    "minecraft:crimson_planks",
-                    "minecraft:warped_planks"
-                )
-                .into_iter()
-                .collect(),
+    /// 与物品相同的池合并为一个, 数量相加
+    fn merge(&mut self, other: &Pool<'a>) -> bool {
+        if self.rolls != other.rolls
+            || self.entries.len() != other.entries.len()
+            || self
+                .entries
+                .iter()
+                .zip(&other.entries)
+                .any(|(a, b)| a.name != b.name || a.data() != b.data())
+        {
+            return false;
+        }
+        let Some(entries) = self
+            .entries
+            .iter()
+            .zip(&other.entries)
+            .map(|(a, b)| {
+                Some(Entry::new(
+                    a.name,
+                    a.count().checked_add(b.count())?,
+                    a.data(),
+                ))
+            })
+            .collect()
+        else {
+            return false;
+        };
+        self.entries = entries;
+        true
+    }
 
-                "minecraft:wooden_slabs" => build_entries!(
-                    count,
-                    "minecraft:oak_slab",
-                    "minecraft:spruce_slab",
-                    "minecraft:birch_slab",
-                    "minecraft:jungle_slab",
-                    "minecraft:acacia_slab",
-                    "minecraft:dark_oak_slab",
-                    "minecraft:mangrove_slab",
-                    "minecraft:cherry_slab",
-                    "minecraft:bamboo_slab"
+    /// 数量超过池中物品的最大堆叠数量时拆成多个池, 每个池各掷一次
+    fn split(self) -> Vec<Pool<'a>> {
+        let limit = self
+            .entries
+            .iter()
+            .map(|e| max_stack_size(e.name))
+            .min()
+            .unwrap_or(DEFAULT_STACK_SIZE);
+        let count = self.entries.first().map_or(1, Entry::count);
+        if count <= limit {
+            return vec![self];
+        }
+        (0..count)
+            .step_by(limit as usize)
+            .map(|start| {
+                Pool::new(
+                    self.entries
+                        .iter()
+                        .map(|e| Entry::new(e.name, (count - start).min(limit), e.data()))
+                        .collect(),
                 )
-                .into_iter()
-                .collect(),
-
-                "minecraft:stone_crafting_materials" | "minecraft:stone_tool_materials" => {
-                    build_entries!(
-                        count,
-                        "minecraft:cobblestone",
-                        "minecraft:cobbled_deepslate",
-                        "minecraft:blackstone"
-                    )
-                    .into_iter()
-                    .collect()
-                }
+            })
+            .collect()
+    }
 
-                "minecraft:logs" => build_entries!(
-                    count,
-                    "minecraft:oak_wood",
-                    "minecraft:stripped_oak_wood",
-                    "minecraft:spruce_wood",
-                    "minecraft:stripped_spruce_wood",
-                    "minecraft:birch_wood",
-                    "minecraft:stripped_birch_wood",
-                    "minecraft:jungle_wood",
-                    "minecraft:stripped_jungle_wood",
-                    "minecraft:acacia_wood",
-                    "minecraft:stripped_acacia_wood",
-                    "minecraft:dark_oak_wood",
-                    "minecraft:stripped_dark_oak_wood",
-                    "minecraft:mangrove_wood",
-                    "minecraft:stripped_mangrove_wood",
-                    "minecraft:cherry_wood",
-                    "minecraft:stripped_cherry_wood",
-                    "minecraft:crimson_hyphae",
-                    "minecraft:warped_hyphae",
-                    "minecraft:stripped_crimson_hyphae",
-                    "minecraft:stripped_warped_hyphae",
-                    "minecraft:oak_log",
-                    "minecraft:spruce_log",
-                    "minecraft:birch_log",
-                    "minecraft:jungle_log",
-                    "minecraft:acacia_log",
-                    "minecraft:dark_oak_log",
-                    "minecraft:mangrove_log",
-                    "minecraft:cherry_log",
-                    "minecraft:crimson_stem",
-                    "minecraft:warped_stem",
-                    "minecraft:stripped_spruce_log",
-                    "minecraft:stripped_birch_log",
-                    "minecraft:stripped_jungle_log",
-                    "minecraft:stripped_acacia_log",
-                    "minecraft:stripped_dark_oak_log",
-                    "minecraft:stripped_oak_log",
-                    "minecraft:stripped_mangrove_log",
-                    "minecraft:stripped_cherry_log",
-                    "minecraft:stripped_crimson_stem",
-                    "minecraft:stripped_warped_stem",
-                    "minecraft:bamboo_block",
-                    "minecraft:stripped_bamboo_block"
-                )
-                .into_iter()
+    fn from_item_tag(value: &ItemTag<'a>, count: u32) -> Result<Self> {
+        Ok(Self::new(
+            tag_items(value.tag)
+                .with_context(|| format!("不支持的的 Tag {}", value.tag))?
+                .iter()
+                .map(|item| Entry::new(item, count, None))
                 .collect(),
+        ))
+    }
 
-                "minecraft:coals" => build_entries!(count, "minecraft:coal", "minecraft:charcoal")
-                    .into_iter()
-                    .collect(),
-
-                "minecraft:soul_fire_base_blocks" => {
-                    build_entries!(count, "minecraft:soul_sand", "minecraft:soul_soil")
-                        .into_iter()
-                        .collect()
-                }
-                "minecraft:wool" => build_entries!(
-                    count,
-                    "minecraft:white_wool",
-                    "minecraft:orange_wool",
-                    "minecraft:magenta_wool",
-                    "minecraft:light_blue_wool",
-                    "minecraft:yellow_wool",
-                    "minecraft:lime_wool",
-                    "minecraft:pink_wool",
-                    "minecraft:gray_wool",
-                    "minecraft:light_gray_wool",
-                    "minecraft:cyan_wool",
-                    "minecraft:purple_wool",
-                    "minecraft:blue_wool",
-                    "minecraft:brown_wool",
-                    "minecraft:green_wool",
-                    "minecraft:red_wool",
-                    "minecraft:black_wool"
-                )
-                .into_iter()
-                .collect(),
-                _ => bail!("不支持的的 Tag {}", value.tag),
-            }),
+    fn outputs(&self) -> Option<Stack> {
+        match self.entries.as_slice() {
+            [] => None,
+            [entry] => Some(Stack::new(
+                Material::item(entry.name, entry.data()),
+                entry.count(),
+            )),
+            entries => Some(Stack::new(
+                Material::random(entries.iter().map(|e| e.name)),
+                entries[0].count(),
+            )),
+        }
+        .map(|stack| {
+            Stack::new(
+                stack.material,
+                stack.count.saturating_mul(self.rolls.into()),
+            )
         })
     }
 }
 
+pub fn tag_items(tag: &str) -> Option<&'static [&'static str]> {
+    Some(match tag {
+        "minecraft:planks" => &[
+            "minecraft:oak_planks",
+            "minecraft:spruce_planks",
+            "minecraft:birch_planks",
+            "minecraft:jungle_planks",
+            "minecraft:acacia_planks",
+            "minecraft:dark_oak_planks",
+            "minecraft:mangrove_planks",
+            "minecraft:cherry_planks",
+            "minecraft:bamboo_planks",
+            "minecraft:crimson_planks",
+            "minecraft:warped_planks",
+        ],
+        "minecraft:wooden_slabs" => &[
+            "minecraft:oak_slab",
+            "minecraft:spruce_slab",
+            "minecraft:birch_slab",
+            "minecraft:jungle_slab",
+            "minecraft:acacia_slab",
+            "minecraft:dark_oak_slab",
+            "minecraft:mangrove_slab",
+            "minecraft:cherry_slab",
+            "minecraft:bamboo_slab",
+        ],
+        "minecraft:stone_crafting_materials" | "minecraft:stone_tool_materials" => &[
+            "minecraft:cobblestone",
+            "minecraft:cobbled_deepslate",
+            "minecraft:blackstone",
+        ],
+        "minecraft:logs" => &[
+            "minecraft:oak_wood",
+            "minecraft:stripped_oak_wood",
+            "minecraft:spruce_wood",
+            "minecraft:stripped_spruce_wood",
+            "minecraft:birch_wood",
+            "minecraft:stripped_birch_wood",
+            "minecraft:jungle_wood",
+            "minecraft:stripped_jungle_wood",
+            "minecraft:acacia_wood",
+            "minecraft:stripped_acacia_wood",
+            "minecraft:dark_oak_wood",
+            "minecraft:stripped_dark_oak_wood",
+            "minecraft:mangrove_wood",
+            "minecraft:stripped_mangrove_wood",
+            "minecraft:cherry_wood",
+            "minecraft:stripped_cherry_wood",
+            "minecraft:crimson_hyphae",
+            "minecraft:warped_hyphae",
+            "minecraft:stripped_crimson_hyphae",
+            "minecraft:stripped_warped_hyphae",
+            "minecraft:oak_log",
+            "minecraft:spruce_log",
+            "minecraft:birch_log",
+            "minecraft:jungle_log",
+            "minecraft:acacia_log",
+            "minecraft:dark_oak_log",
+            "minecraft:mangrove_log",
+            "minecraft:cherry_log",
+            "minecraft:crimson_stem",
+            "minecraft:warped_stem",
+            "minecraft:stripped_spruce_log",
+            "minecraft:stripped_birch_log",
+            "minecraft:stripped_jungle_log",
+            "minecraft:stripped_acacia_log",
+            "minecraft:stripped_dark_oak_log",
+            "minecraft:stripped_oak_log",
+            "minecraft:stripped_mangrove_log",
+            "minecraft:stripped_cherry_log",
+            "minecraft:stripped_crimson_stem",
+            "minecraft:stripped_warped_stem",
+            "minecraft:bamboo_block",
+            "minecraft:stripped_bamboo_block",
+        ],
+        "minecraft:coals" => &["minecraft:coal", "minecraft:charcoal"],
+        "minecraft:soul_fire_base_blocks" => &["minecraft:soul_sand", "minecraft:soul_soil"],
+        "minecraft:wool" => &[
+            "minecraft:white_wool",
+            "minecraft:orange_wool",
+            "minecraft:magenta_wool",
+            "minecraft:light_blue_wool",
+            "minecraft:yellow_wool",
+            "minecraft:lime_wool",
+            "minecraft:pink_wool",
+            "minecraft:gray_wool",
+            "minecraft:light_gray_wool",
+            "minecraft:cyan_wool",
+            "minecraft:purple_wool",
+            "minecraft:blue_wool",
+            "minecraft:brown_wool",
+            "minecraft:green_wool",
+            "minecraft:red_wool",
+            "minecraft:black_wool",
+        ],
+        _ => return None,
+    })
+}
+
 impl<'a> From<ItemStack<'a>> for Pool<'a> {
     fn from(value: ItemStack<'a>) -> Self {
         Self::new(vec![Entry::from_item_stack(value)])
@@ -240,43 +309,54 @@ pub struct LootTable<'a> {
 }
 
 impl<'a> LootTable<'a> {
+    fn push(&mut self, pool: Pool<'a>) {
+        if !self.pools.iter_mut().any(|p| p.merge(&pool)) {
+            self.pools.push(pool);
+        }
+    }
+
+    fn split(self) -> Self {
+        Self {
+            pools: self.pools.into_iter().flat_map(Pool::split).collect(),
+        }
+    }
+
     pub fn from_vec_ingredient(value: Vec<Ingredient<'a>>) -> Result<Self> {
-        Ok(LootTable {
-            pools: value.into_iter().try_fold(vec![], |mut acc, i| {
+        value
+            .into_iter()
+            .try_fold(LootTable { pools: vec![] }, |mut acc, i| {
                 acc.push(match i {
                     Ingredient::Item(item_stack) => item_stack.into(),
                     Ingredient::Tag(item_tag) => Pool::from_item_tag(&item_tag, 1)?,
                 });
-                Ok::<Vec<Pool<'_>>, anyhow::Error>(acc)
-            })?,
-        })
+                Ok(acc)
+            })
+            .map(Self::split)
     }
 
     pub fn from_shaped(shaped: Shaped<'a>) -> Result<Self> {
-        Ok(Self {
-            pools: shaped
-                .key
-                .into_iter()
-                .try_fold(vec![], |mut acc, (k, i)| {
-                    let count = shaped
-                        .pattern
-                        .iter()
-                        .map(|s| s.chars().filter(|&c| c == k).count())
-                        .sum::<usize>() as u8;
-                    acc.push(match i {
-                        Key::Item(pair) => ItemStack {
-                            item: pair.item,
-                            data: pair.data,
-                            count: Some(count),
-                        }
-                        .into(),
-                        Key::Tag(item_tag) => Pool::from_item_tag(&item_tag, count)?,
-                    });
-                    Ok::<Vec<Pool<'_>>, anyhow::Error>(acc)
-                })?
-                .into_iter()
-                .collect(),
-        })
+        shaped
+            .key
+            .iter()
+            .map(|(&k, &i)| (shaped.count_key(k), i))
+            .filter(|&(count, _)| count > 0)
+            .try_fold(LootTable { pools: vec![] }, |mut acc, (count, i)| {
+                acc.push(match i {
+                    Key::Item(pair) => ItemStack {
+                        item: pair.item,
+                        data: pair.data,
+                        count: Some(count),
+                    }
+                    .into(),
+                    Key::Tag(item_tag) => Pool::from_item_tag(&item_tag, count)?,
+                });
+                Ok(acc)
+            })
+            .map(Self::split)
+    }
+
+    pub fn outputs(&self) -> Vec<Stack> {
+        self.pools.iter().filter_map(Pool::outputs).collect()
     }
 }
 
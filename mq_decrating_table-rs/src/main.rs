@@ -1,95 +1,160 @@
 use anyhow::{bail, ensure, Context, Result};
-use recipe::Recipe;
+use clap::{Parser, Subcommand};
+use config::Config;
+use diff::Diff;
+use exploit::Analyzer;
+use manifest::Manifests;
+use output::Output;
+use package::Package;
+use recipe::{local_name, Recipe, Target};
+use resource_pack::{Assets, Vanilla};
 use rustc_hash::FxHashSet;
-use std::fs::{self, DirEntry};
+use samples::{recipe_files, Samples};
+use script::ScriptManifest;
+use std::{fs, path::PathBuf};
+use validate::Validator;
+mod config;
+mod diff;
+mod exploit;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod golden;
 mod loot_table;
+mod manifest;
+mod output;
+mod package;
 mod recipe;
+mod resource_pack;
+mod samples;
+mod script;
+#[cfg(test)]
+mod simulator;
+mod stack_size;
+mod validate;
+mod version;
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-const ITEM_TEMPLATE: &'static str = include_str!("item.json");
-const TARGT: &'static str = "../pack/mq_decrafting_table_bp/";
-
-static mut RESULT_ID: String = String::new();
-
-fn process(entry: DirEntry, ids: &mut FxHashSet<String>) -> Result<()> {
-    let path = entry.path();
-    let filename = path.file_name().unwrap().to_str().unwrap();
-    println!("读取配方文件: {}", filename);
-
-    ensure!(!filename.contains("_from_"), "跳过_from_的配方");
-    let s = fs::read_to_string(&path)
-        .context("无法读取配方文件")?
-        .trim_end()
-        .to_owned();
-    let source: Recipe = serde_json::from_str(&s).context("反序列化配方失败")?;
-    match source.component {
-        Some(component) => {
-            println!("开始处理: {}", component.description.identifier);
-            ensure!(
-                !ids.contains(component.description.identifier.as_ref()),
-                "跳过重复的配方"
-            );
-
-            ids.insert(component.description.identifier.clone().into_owned());
-            ensure!(!component.is_deprecated(), "跳过弃用的配方");
-            let result_id = component
-                .description
-                .identifier
-                .to_string()
-                .replace("minecraft:", "mq_decrafting_table:");
-            unsafe { RESULT_ID.clear() };
-
-            let (recipe, table) = component
-                .inverse(&result_id, unsafe {
-                    std::ptr::addr_of_mut!(RESULT_ID).as_mut().unwrap()
-                })
-                .context("生成配方失败")?;
-
-            if let Some(recipe) = recipe {
-                fs::write(
-                    TARGT.to_owned() + "recipes/decrafting/" + filename,
-                    serde_json::to_string(&recipe)?,
-                )
-                .context("写入配方失败")?;
-            } else {
-                bail!("无法生成配方");
-            }
+const ITEM_TEMPLATE: &str = include_str!("item.json");
 
-            if let Some(loot_table) = table {
-                let c = serde_json::to_string(&loot_table)?;
-                let id = unsafe { &RESULT_ID.get_unchecked(19..) };
-                fs::write(
-                    TARGT.to_owned() + "loot_tables/decrafting/" + id + ".json",
-                    c,
-                )
-                .context("写入loot_table失败")?;
-
-                fs::write(
-                    TARGT.to_owned() + "items/decrafting/" + id + ".json",
-                    ITEM_TEMPLATE.replace("$IDENTIFIER", id),
-                )
-                .context("写入item失败")?;
-            }
+#[derive(Subcommand)]
+enum Command {
+    /// 比较两个 bedrock-samples 版本生成的分解配方与战利品表
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// 将报告写入文件而不是标准输出
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// 发现复制漏洞时终止构建
+    #[arg(long)]
+    deny_exploits: bool,
+    /// 配置文件路径
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// bedrock-samples 文件夹或发布压缩包
+    #[arg(long, default_value = "../bedrock-samples")]
+    samples: PathBuf,
+    /// 额外的附加包配方文件夹
+    #[arg(long = "addon")]
+    addons: Vec<PathBuf>,
+    /// 记录生成的文件, 跳过未变化的写入并删除过期文件
+    #[arg(long)]
+    managed: bool,
+    /// 将行为包与资源包打包为 .mcaddon 或 .mcpack
+    #[arg(long)]
+    package: Option<PathBuf>,
+    /// 发布标签, 用于替换语言文件中的 $TAG 并设置清单版本
+    #[arg(long)]
+    tag: Option<String>,
+    /// 打包时放入行为包 scripts 文件夹的脚本
+    #[arg(long)]
+    scripts: Option<PathBuf>,
+    /// 跳过生成, 只打包已有的行为包与资源包
+    #[arg(long, requires = "package")]
+    no_generate: bool,
+}
+
+struct Generator<'a> {
+    config: &'a Config,
+    vanilla: Vanilla,
+    ids: FxHashSet<String>,
+    analyzer: Analyzer,
+    assets: Assets,
+    script: ScriptManifest,
+    manifests: Manifests,
+    item_version: String,
+    validator: Validator,
+    dropped: Vec<String>,
+    output: Output,
+}
+
+impl<'a> Generator<'a> {
+    fn new(config: &'a Config, samples: &mut Samples, output: Output) -> Result<Self> {
+        Ok(Self {
+            config,
+            vanilla: Vanilla::load(samples)?,
+            ids: FxHashSet::default(),
+            analyzer: Analyzer::default(),
+            assets: Assets::default(),
+            script: ScriptManifest::new(&config.item_namespace),
+            manifests: Manifests::default(),
+            item_version: version::resolve(
+                "",
+                &[version::ITEM],
+                config.item_format_version.as_deref(),
+            )?,
+            validator: Validator::new()?,
+            dropped: vec![],
+            output,
+        })
+    }
+
+    fn run(&mut self, samples: &mut Samples, addons: &[PathBuf], tag: Option<&str>) -> Result<()> {
+        println!("读取源文件夹: {}", samples.display());
+        for file in samples.recipes().context("读取源文件夹失败")? {
+            let s = samples.read(&file)?.context("无法读取配方文件")?;
+            self.report(&file, s);
         }
-        None => {
-            bail!("跳过其他配方");
+        for addon in addons {
+            println!("读取附加包文件夹: {}", addon.display());
+            let mut files = vec![];
+            recipe_files(addon, &mut files).context("读取附加包文件夹失败")?;
+            files.sort();
+            for path in files {
+                let s = fs::read_to_string(&path).context("无法读取配方文件")?;
+                self.report(&path.to_string_lossy(), s);
+            }
         }
+
+        self.assets
+            .write(
+                &mut self.output,
+                &self.config.resource_pack,
+                &self.config.item_namespace,
+            )
+            .context("写入资源包失败")?;
+        self.script
+            .write(&mut self.output, &self.config.script_manifest)?;
+        self.manifests.write(
+            &mut self.output,
+            &self.config.behavior_pack,
+            &self.config.resource_pack,
+            tag,
+        )
     }
-    Ok(())
-}
 
-fn main() -> Result<()> {
-    let source = fs::read_dir("../bedrock-samples")?
-        .next()
-        .unwrap()?
-        .path()
-        .join("behavior_pack/recipes");
-    println!("读取源文件夹: {}", source.display());
-    let mut ids: FxHashSet<String> = FxHashSet::default();
-    for entry in fs::read_dir(&source).context("读取源文件夹失败")? {
-        match process(entry?, &mut ids) {
+    fn report(&mut self, path: &str, s: String) {
+        match self.process(path, s) {
             Ok(_) => {
                 println!("处理成功");
             }
@@ -98,5 +163,196 @@ fn main() -> Result<()> {
             }
         }
     }
+
+    fn process(&mut self, path: &str, s: String) -> Result<()> {
+        let Self {
+            config,
+            vanilla,
+            ids,
+            analyzer,
+            assets,
+            script,
+            manifests,
+            item_version,
+            validator,
+            dropped,
+            output,
+        } = self;
+        let filename = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        println!("读取配方文件: {}", filename);
+
+        let source: Recipe = serde_json::from_str(s.trim_end()).context("反序列化配方失败")?;
+        manifests.add_format_version(source.format_version);
+        match source.component {
+            Some(component) => {
+                if !component.is_deprecated() {
+                    analyzer.add_forward(component.flow());
+                }
+                ensure!(!filename.contains("_from_"), "跳过_from_的配方");
+                ensure!(config.accepts(&component), "跳过被配置过滤的配方");
+                println!("开始处理: {}", component.description.identifier);
+                ensure!(
+                    !ids.contains(component.description.identifier.as_ref()),
+                    "跳过重复的配方"
+                );
+
+                ids.insert(component.description.identifier.clone().into_owned());
+                ensure!(!component.is_deprecated(), "跳过弃用的配方");
+                let name = local_name(&component.description.identifier).into_owned();
+                let result_id = format!("{}:{name}", config.recipe_namespace);
+                let source_id = component.description.identifier.clone();
+                let source_item = component.result_item()?;
+                let placeholder = component.placeholder_name();
+                let result_item_id = format!("{}:{placeholder}", config.item_namespace);
+
+                let (recipe, table) = component
+                    .inverse(Target {
+                        recipe_id: &result_id,
+                        item_id: &result_item_id,
+                        crafting_tag: &config.crafting_tag,
+                        unlock: config.unlock,
+                    })
+                    .context("生成配方失败")?;
+
+                let Some(mut recipe) = recipe else {
+                    bail!("无法生成配方");
+                };
+                let Some(component) = &recipe.component else {
+                    bail!("无法生成配方");
+                };
+                analyzer.add_inverse(component.flow());
+                if !component.dropped.is_empty() {
+                    dropped.push(format!("{source_id}: {}", component.dropped.join(", ")));
+                }
+                let format_version = version::resolve(
+                    source.format_version,
+                    &component.features(),
+                    config.format_version.as_deref(),
+                )?;
+                recipe.format_version = &format_version;
+                let path = config
+                    .behavior_pack
+                    .join(format!("recipes/decrafting/{name}.json"));
+                let recipe = serde_json::to_value(&recipe)?;
+                validator.recipe(&path, &recipe);
+                output
+                    .write(path, serde_json::to_string(&recipe)?)
+                    .context("写入配方失败")?;
+
+                if let Some(loot_table) = table {
+                    let outputs = loot_table.outputs();
+                    script.add(
+                        &result_item_id,
+                        &format!("decrafting/{placeholder}"),
+                        &source_id,
+                        &outputs,
+                    );
+                    analyzer.add_loot(result_item_id.clone(), outputs);
+                    let path = config
+                        .behavior_pack
+                        .join(format!("loot_tables/decrafting/{placeholder}.json"));
+                    let loot_table = serde_json::to_value(&loot_table)?;
+                    validator.loot_table(&path, &loot_table);
+                    output
+                        .write(path, serde_json::to_string(&loot_table)?)
+                        .context("写入loot_table失败")?;
+
+                    let (display_name, icon) = assets.add_item(
+                        vanilla,
+                        &result_item_id,
+                        source_item,
+                        config.display_name.as_deref(),
+                    );
+                    let path = config
+                        .behavior_pack
+                        .join(format!("items/decrafting/{placeholder}.json"));
+                    let item = ITEM_TEMPLATE
+                        .replace("$FORMAT_VERSION", item_version)
+                        .replace("$IDENTIFIER", &result_item_id)
+                        .replace("$ICON", &icon)
+                        .replace("$DISPLAY_NAME", &display_name);
+                    validator.item(&path, &serde_json::from_str(&item)?);
+                    output.write(path, item).context("写入item失败")?;
+                }
+            }
+            None => {
+                bail!("跳过其他配方");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    if let Some(Command::Diff { old, new, out }) = &args.command {
+        let [old, new] = [old, new].map(|path| -> Result<_> {
+            let mut samples = Samples::open(path)?;
+            let mut generator = Generator::new(&config, &mut samples, Output::memory())?;
+            generator.run(&mut samples, &args.addons, None)?;
+            Ok(generator.output.into_files())
+        });
+        let diff = Diff::new(&config.behavior_pack, &old?, &new?).to_string();
+        match out {
+            Some(path) => fs::write(path, diff).context("写入差异报告失败")?,
+            None => println!("{diff}"),
+        }
+        return Ok(());
+    }
+
+    if !args.no_generate {
+        generate(&args, &config)?;
+    }
+    if let Some(out) = &args.package {
+        Package {
+            tag: args.tag.as_deref(),
+            scripts: args.scripts.as_deref(),
+        }
+        .write(out, &config.behavior_pack, &config.resource_pack)
+        .context("打包失败")?;
+    }
+    Ok(())
+}
+
+fn generate(args: &Args, config: &Config) -> Result<()> {
+    let mut samples = Samples::open(&args.samples)?;
+    let mut generator = Generator::new(
+        config,
+        &mut samples,
+        Output::new(args.managed.then(|| config.output_manifest.clone()))?,
+    )?;
+    generator.run(&mut samples, &args.addons, args.tag.as_deref())?;
+    for dropped in &generator.dropped {
+        println!("丢弃的字段: {dropped}");
+    }
+    let violations = &generator.validator.violations;
+    for violation in violations {
+        eprintln!("无效的输出: {violation}");
+    }
+    ensure!(
+        violations.is_empty(),
+        "发现 {} 处无效的输出",
+        violations.len()
+    );
+
+    let exploits = generator.analyzer.run();
+    for exploit in &exploits {
+        println!("发现复制漏洞: {exploit}");
+    }
+    ensure!(
+        !args.deny_exploits || exploits.is_empty(),
+        "发现 {} 个复制漏洞",
+        exploits.len()
+    );
+
+    if let Some(report) = generator.output.finish()? {
+        println!("{report}");
+    }
     Ok(())
 }
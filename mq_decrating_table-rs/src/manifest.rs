@@ -0,0 +1,139 @@
+use crate::{
+    output::{to_pretty, Output},
+    version::{self, Version},
+};
+use anyhow::{ensure, Context, Result};
+use rustc_hash::FxHashSet;
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+/// 版本号取标签中的最后三段数字, 如 `v1.21.50.7` 为 `[21, 50, 7]`
+pub fn version(tag: &str) -> Result<Version> {
+    let numbers = tag
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|n| !n.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<u32>, _>>()?;
+    ensure!(numbers.len() >= 3, "无法从标签解析版本号: {tag}");
+    Ok(numbers[numbers.len() - 3..].try_into()?)
+}
+
+fn read(pack: &Path) -> Result<Value> {
+    let path = pack.join("manifest.json");
+    serde_json::from_str(
+        &fs::read_to_string(&path).with_context(|| format!("无法读取清单: {}", path.display()))?,
+    )
+    .with_context(|| format!("解析清单失败: {}", path.display()))
+}
+
+fn uuid(manifest: &Value) -> Result<&str> {
+    manifest["header"]["uuid"]
+        .as_str()
+        .context("清单缺少 header.uuid")
+}
+
+/// 只替换指向另一个包的依赖, 保留其他依赖
+fn set_dependency(manifest: &mut Value, other: &Value) -> Result<()> {
+    let uuid = uuid(other)?;
+    let dependency = json!({
+        "uuid": uuid,
+        "version": other["header"]["version"],
+    });
+    let dependencies = manifest
+        .as_object_mut()
+        .context("清单不是对象")?
+        .entry("dependencies")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .context("清单的 dependencies 不是数组")?;
+    match dependencies.iter_mut().find(|d| d["uuid"] == uuid) {
+        Some(existing) => *existing = dependency,
+        None => dependencies.push(dependency),
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Manifests {
+    min_engine_version: Version,
+}
+
+impl Manifests {
+    pub fn add_format_version(&mut self, format_version: &str) {
+        if let Some(version) = version::parse(format_version) {
+            self.min_engine_version = self.min_engine_version.max(version);
+        }
+    }
+
+    pub fn write(
+        &self,
+        output: &mut Output,
+        behavior_pack: &Path,
+        resource_pack: &Path,
+        tag: Option<&str>,
+    ) -> Result<()> {
+        let mut manifests = [read(behavior_pack)?, read(resource_pack)?];
+        for manifest in &mut manifests {
+            let header = &mut manifest["header"];
+            let current: Version =
+                serde_json::from_value(header["min_engine_version"].take()).unwrap_or_default();
+            header["min_engine_version"] = current.max(self.min_engine_version).as_slice().into();
+            if let Some(tag) = tag {
+                let version = version(tag)?;
+                manifest["header"]["version"] = version.as_slice().into();
+                if let Some(modules) = manifest["modules"].as_array_mut() {
+                    for module in modules {
+                        module["version"] = version.as_slice().into();
+                    }
+                }
+            }
+        }
+
+        let mut uuids = FxHashSet::default();
+        for manifest in &manifests {
+            let modules = manifest["modules"].as_array().into_iter().flatten();
+            for uuid in
+                std::iter::once(uuid(manifest)?).chain(modules.filter_map(|m| m["uuid"].as_str()))
+            {
+                ensure!(uuids.insert(uuid), "清单中有重复的 UUID: {uuid}");
+            }
+        }
+
+        let [mut bp, mut rp] = manifests;
+        set_dependency(&mut bp, &rp)?;
+        set_dependency(&mut rp, &bp)?;
+        for (pack, manifest) in [(behavior_pack, bp), (resource_pack, rp)] {
+            output
+                .merge(pack.join("manifest.json"), to_pretty(&manifest)?)
+                .context("写入清单失败")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_dependency_keeps_other_dependencies() {
+        let mut bp = json!({
+            "header": { "uuid": "bp", "version": [1, 0, 0] },
+            "dependencies": [
+                { "uuid": "other", "version": [2, 0, 0] },
+                { "uuid": "rp", "version": [1, 0, 0] },
+                { "module_name": "@minecraft/server", "version": "1.0.0" }
+            ]
+        });
+        let rp = json!({ "header": { "uuid": "rp", "version": [1, 21, 50] } });
+        set_dependency(&mut bp, &rp).unwrap();
+        assert_eq!(
+            bp["dependencies"],
+            json!([
+                { "uuid": "other", "version": [2, 0, 0] },
+                { "uuid": "rp", "version": [1, 21, 50] },
+                { "module_name": "@minecraft/server", "version": "1.0.0" }
+            ])
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{ensure, Context, Result};
+use rustc_hash::FxHasher;
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Serializer};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+fn hash(contents: &[u8]) -> String {
+    let mut hasher = FxHasher::default();
+    hasher.write(contents);
+    format!("{:016x}", hasher.finish())
+}
+
+pub fn to_pretty(value: &impl Serialize) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    value.serialize(&mut Serializer::with_formatter(
+        &mut buf,
+        PrettyFormatter::with_indent(b"\t"),
+    ))?;
+    Ok(buf)
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, files) in [
+            ("新增", &self.added),
+            ("修改", &self.changed),
+            ("删除", &self.removed),
+        ] {
+            for file in files {
+                writeln!(f, "{label}: {file}")?;
+            }
+        }
+        write!(
+            f,
+            "新增 {} 个, 修改 {} 个, 删除 {} 个, 未变 {} 个",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+}
+
+pub struct Output {
+    manifest: Option<PathBuf>,
+    previous: BTreeMap<String, String>,
+    current: BTreeMap<String, String>,
+    written: BTreeSet<String>,
+    pending: BTreeMap<PathBuf, Vec<u8>>,
+    memory: Option<BTreeMap<String, Vec<u8>>>,
+    report: Report,
+}
+
+impl Output {
+    pub fn new(manifest: Option<PathBuf>) -> Result<Self> {
+        let previous = match &manifest {
+            Some(path) if path.exists() => {
+                serde_json::from_str(&fs::read_to_string(path).context("无法读取输出清单")?)
+                    .context("解析输出清单失败")?
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Self {
+            manifest,
+            previous,
+            current: BTreeMap::new(),
+            written: BTreeSet::new(),
+            pending: BTreeMap::new(),
+            memory: None,
+            report: Report::default(),
+        })
+    }
+
+    pub fn memory() -> Self {
+        Self {
+            manifest: None,
+            previous: BTreeMap::new(),
+            current: BTreeMap::new(),
+            written: BTreeSet::new(),
+            pending: BTreeMap::new(),
+            memory: Some(BTreeMap::new()),
+            report: Report::default(),
+        }
+    }
+
+    pub fn into_files(self) -> BTreeMap<String, Vec<u8>> {
+        self.memory.unwrap_or_default()
+    }
+
+    pub fn write(&mut self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        self.put(path.as_ref(), contents.as_ref(), true)
+    }
+
+    /// 写入与其他内容合并的文件, 不记入输出清单, 因此不会被当作过期文件删除
+    pub fn merge(&mut self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        self.put(path.as_ref(), contents.as_ref(), false)
+    }
+
+    fn put(&mut self, path: &Path, contents: &[u8], owned: bool) -> Result<()> {
+        let key = path.to_string_lossy().into_owned();
+        ensure!(
+            !owned || self.written.insert(key.clone()),
+            "重复写入同一文件: {key}"
+        );
+        if let Some(memory) = &mut self.memory {
+            memory.insert(key, contents.to_owned());
+            return Ok(());
+        }
+        if self.manifest.is_some() {
+            let previous = if owned {
+                self.previous.contains_key(&key)
+            } else {
+                self.previous.remove(&key).is_some()
+            };
+            match fs::read(path) {
+                Ok(existing) if existing == contents => {
+                    self.report.unchanged += 1;
+                    if owned {
+                        self.current.insert(key, hash(contents));
+                    }
+                    return Ok(());
+                }
+                Ok(_) => self.report.changed.push(key.clone()),
+                Err(_) if previous => self.report.changed.push(key.clone()),
+                Err(_) => self.report.added.push(key.clone()),
+            }
+        }
+        self.pending.insert(path.to_owned(), contents.to_owned());
+        if owned {
+            self.current.insert(key, hash(contents));
+        }
+        Ok(())
+    }
+
+    /// 写入暂存的文件, 删除过期文件并更新输出清单; 在此之前不会改动磁盘
+    pub fn finish(mut self) -> Result<Option<Report>> {
+        for (path, contents) in &self.pending {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)
+                .with_context(|| format!("写入文件失败: {}", path.display()))?;
+        }
+        let Some(manifest) = self.manifest else {
+            return Ok(None);
+        };
+        for file in self.previous.keys() {
+            if !self.current.contains_key(file) {
+                if Path::new(file).exists() {
+                    fs::remove_file(file).with_context(|| format!("删除过期文件失败: {file}"))?;
+                }
+                self.report.removed.push(file.clone());
+            }
+        }
+        fs::write(&manifest, serde_json::to_string_pretty(&self.current)?)
+            .context("写入输出清单失败")?;
+        Ok(Some(self.report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_writes_fail() {
+        let mut output = Output::memory();
+        output.write("items/decrafting/stick.json", "{}").unwrap();
+        output.merge("texts/en_US.lang", "").unwrap();
+        output.merge("texts/en_US.lang", "").unwrap();
+        assert!(output.write("items/decrafting/stick.json", "{}").is_err());
+    }
+
+    #[test]
+    fn writes_wait_for_finish() {
+        let dir = std::env::temp_dir().join("mq_decrafting_table_output_test");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("items/stick.json");
+        let mut output = Output::new(None).unwrap();
+        output.write(&path, "{}").unwrap();
+        assert!(!path.exists());
+        output.finish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
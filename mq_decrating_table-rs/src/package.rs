@@ -0,0 +1,100 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+fn files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("读取文件夹失败: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let name = format!("{prefix}{}", path.file_name().unwrap().to_string_lossy());
+        if path.is_dir() {
+            self::files(&path, &format!("{name}/"), files)?;
+        } else if !name.ends_with(".gitkeep") {
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+pub struct Package<'a> {
+    pub tag: Option<&'a str>,
+    pub scripts: Option<&'a Path>,
+}
+
+impl Package<'_> {
+    fn contents(&self, name: &str, path: &Path) -> Result<Vec<u8>> {
+        let contents =
+            fs::read(path).with_context(|| format!("无法读取文件: {}", path.display()))?;
+        let Some(tag) = self.tag else {
+            return Ok(contents);
+        };
+        if name.ends_with(".lang") {
+            return Ok(String::from_utf8(contents)?
+                .replace("$TAG", tag)
+                .into_bytes());
+        }
+        Ok(contents)
+    }
+
+    fn pack_files(&self, pack: &Path, scripts: bool) -> Result<Vec<(String, PathBuf)>> {
+        let mut pack_files = vec![];
+        files(pack, "", &mut pack_files)?;
+        if let Some(dir) = self.scripts.filter(|_| scripts) {
+            files(dir, "scripts/", &mut pack_files)?;
+        }
+        pack_files.sort();
+        Ok(pack_files)
+    }
+
+    fn write_zip(&self, out: &Path, packs: &[(&str, &Path, bool)]) -> Result<()> {
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut zip = ZipWriter::new(File::create(out)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for &(prefix, pack, scripts) in packs {
+            for (name, path) in self.pack_files(pack, scripts)? {
+                zip.start_file(format!("{prefix}{name}"), options)?;
+                zip.write_all(&self.contents(&name, &path)?)?;
+            }
+        }
+        zip.finish()?;
+        println!("写入压缩包: {}", out.display());
+        Ok(())
+    }
+
+    /// `.mcaddon` 包含两个包的文件夹, `.mcpack` 则为每个包各写一个压缩包
+    pub fn write(&self, out: &Path, behavior_pack: &Path, resource_pack: &Path) -> Result<()> {
+        let packs = [(behavior_pack, true), (resource_pack, false)];
+        match out.extension().and_then(|e| e.to_str()) {
+            Some("mcaddon") => {
+                let prefix = |pack: &Path| {
+                    format!(
+                        "{}/",
+                        pack.file_name().unwrap_or_default().to_string_lossy()
+                    )
+                };
+                let (bp, rp) = (prefix(behavior_pack), prefix(resource_pack));
+                self.write_zip(
+                    out,
+                    &[(&bp, behavior_pack, true), (&rp, resource_pack, false)],
+                )
+            }
+            Some("mcpack") => {
+                let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+                for ((pack, scripts), suffix) in packs.into_iter().zip(["bp", "rp"]) {
+                    self.write_zip(
+                        &out.with_file_name(format!("{stem}_{suffix}.mcpack")),
+                        &[("", pack, scripts)],
+                    )?;
+                }
+                Ok(())
+            }
+            _ => bail!("不支持的打包格式: {}", out.display()),
+        }
+    }
+}
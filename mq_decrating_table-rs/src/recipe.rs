@@ -1,12 +1,18 @@
-use crate::loot_table::LootTable;
-use anyhow::{bail, Result};
-use rustc_hash::FxHashMap;
-use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use serde_json::Value;
-use std::{borrow::Cow, char};
+use crate::{
+    config::Unlock,
+    exploit::{gcd, Flow, Material, Stack},
+    loot_table::LootTable,
+    stack_size::max_stack_size,
+    version::{self, Version},
+};
+use anyhow::{bail, ensure, Context, Result};
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::{borrow::Cow, char, collections::BTreeMap};
 
 const CHARS: [char; 9] = ['A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I'];
-const BUCKET: &'static str = "minecraft:bucket";
+/// 原料中表示任意数据值的通配值, 旧版配方也写作 -1
+pub const ANY_DATA: u16 = 32767;
 
 impl<'a> From<ItemStack<'a>> for ItemPair<'a> {
     #[inline(always)]
@@ -18,14 +24,78 @@ impl<'a> From<ItemStack<'a>> for ItemPair<'a> {
     }
 }
 
+/// 物品的各种写法: `"minecraft:wool"`, `"minecraft:wool:3"` 或 `{ "item": ..., "data": ..., "count": ... }`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawItem<'a> {
+    Id(&'a str),
+    Object {
+        item: &'a str,
+        #[serde(default)]
+        data: Option<i32>,
+        #[serde(default)]
+        count: Option<u32>,
+    },
+}
+
+fn data_value(id: &str, data: i32) -> Result<u16, String> {
+    match data {
+        ..0 => Ok(ANY_DATA),
+        0..=32767 => Ok(data as u16),
+        _ => Err(format!("物品 {id} 的数据值 {data} 超出范围")),
+    }
+}
+
+/// 拆出物品 ID 末尾的 `:data` 后缀, 没有命名空间时整体视为 ID
+fn split_data(id: &str) -> Result<(&str, Option<u16>), String> {
+    match id.rsplit_once(':') {
+        Some((item, data)) if item.contains(':') && data.bytes().all(|b| b.is_ascii_digit()) => {
+            let data = data
+                .parse()
+                .map_err(|_| format!("无法解析物品 {id} 的数据值"))?;
+            Ok((item, Some(data_value(id, data)?)))
+        }
+        _ => Ok((id, None)),
+    }
+}
+
+impl<'a> TryFrom<RawItem<'a>> for ItemStack<'a> {
+    type Error = String;
+
+    fn try_from(value: RawItem<'a>) -> Result<Self, Self::Error> {
+        let (id, data, count) = match value {
+            RawItem::Id(id) => (id, None, None),
+            RawItem::Object { item, data, count } => (item, data, count),
+        };
+        let (item, suffix) = split_data(id)?;
+        if data.is_some() && suffix.is_some() {
+            return Err(format!("物品 {id} 同时指定了 data 字段与数据值后缀"));
+        }
+        Ok(Self {
+            item,
+            data: data.map(|d| data_value(id, d)).transpose()?.or(suffix),
+            count,
+        })
+    }
+}
+
+impl<'a> TryFrom<RawItem<'a>> for ItemPair<'a> {
+    type Error = String;
+
+    fn try_from(value: RawItem<'a>) -> Result<Self, Self::Error> {
+        ItemStack::try_from(value).map(Self::from)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(try_from = "RawItem")]
 pub struct ItemPair<'a> {
     pub item: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<u8>,
+    pub data: Option<u16>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 #[serde(untagged)]
 pub enum Key<'a> {
     #[serde(borrow)]
@@ -35,18 +105,22 @@ pub enum Key<'a> {
 
 impl<'a> Key<'a> {
     #[inline(always)]
-    fn take_item(self) -> ItemPair<'a> {
-        unsafe { (&self as *const Key as *const ItemPair).read() }
+    fn take_item(&self) -> Result<ItemPair<'a>> {
+        match self {
+            Key::Item(pair) => Ok(*pair),
+            Key::Tag(tag) => bail!("不支持的 Tag 原料: {}", tag.tag),
+        }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(try_from = "RawItem")]
 pub struct ItemStack<'a> {
     pub item: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<u8>,
+    pub data: Option<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub count: Option<u8>,
+    pub count: Option<u32>,
 }
 
 impl<'a> std::fmt::Display for ItemStack<'a> {
@@ -63,11 +137,38 @@ impl<'a> std::fmt::Display for ItemStack<'a> {
 }
 
 impl<'a> ItemStack<'a> {
+    /// 作为产物时去掉通配数据值, 给出默认的物品
+    #[inline(always)]
+    pub fn concrete(self) -> Self {
+        Self {
+            data: self.data.filter(|&d| d != ANY_DATA),
+            ..self
+        }
+    }
+
+    /// 超过最大堆叠数量时拆成多组, 数量为 0 时 (如 pattern 中未使用的 key) 不产出
+    fn split(self) -> impl Iterator<Item = ItemStack<'a>> {
+        let count = self.count.unwrap_or(1);
+        let limit = max_stack_size(self.item);
+        let chunks = if count == 0 {
+            vec![]
+        } else if count > limit {
+            (0..count)
+                .step_by(limit as usize)
+                .map(|start| Some((count - start).min(limit)))
+                .collect()
+        } else {
+            vec![self.count]
+        };
+        chunks.into_iter().map(move |count| Self { count, ..self })
+    }
+
+    /// 占位物品只产出一个, 打开时掷一次战利品表, 对应一次合成所用的原料
     const fn crate_mq(&self, id: &'a str) -> ItemStack<'a> {
         Self {
             item: id,
             data: self.data,
-            count: self.count,
+            count: None,
         }
     }
 }
@@ -88,6 +189,36 @@ pub struct ItemTag<'a> {
     pub tag: &'a str,
 }
 
+impl<'a> From<&ItemStack<'a>> for Stack {
+    #[inline(always)]
+    fn from(value: &ItemStack<'a>) -> Self {
+        Stack::new(
+            Material::item(value.item, value.data),
+            value.count.unwrap_or(1),
+        )
+    }
+}
+
+impl<'a> From<&Ingredient<'a>> for Stack {
+    #[inline(always)]
+    fn from(value: &Ingredient<'a>) -> Self {
+        match value {
+            Ingredient::Item(item) => item.into(),
+            Ingredient::Tag(tag) => Stack::new(Material::tag(tag.tag), 1),
+        }
+    }
+}
+
+impl<'a> From<&Key<'a>> for Material {
+    #[inline(always)]
+    fn from(value: &Key<'a>) -> Self {
+        match value {
+            Key::Item(pair) => Material::item(pair.item, pair.data),
+            Key::Tag(tag) => Material::tag(tag.tag),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
 #[serde(untagged)]
 pub enum Ingredient<'a> {
@@ -99,8 +230,11 @@ pub enum Ingredient<'a> {
 
 impl<'a> Ingredient<'a> {
     #[inline(always)]
-    fn take_item(self) -> ItemStack<'a> {
-        unsafe { (&self as *const Ingredient as *const ItemStack).read() }
+    fn take_item(self) -> Result<ItemStack<'a>> {
+        match self {
+            Ingredient::Item(item) => Ok(item),
+            Ingredient::Tag(tag) => bail!("不支持的 Tag 原料: {}", tag.tag),
+        }
     }
 }
 
@@ -116,7 +250,7 @@ impl<'a> From<ItemStacks<'a>> for Vec<Ingredient<'a>> {
     fn from(value: ItemStacks<'a>) -> Self {
         match value {
             ItemStacks::Single(i) => vec![Ingredient::Item(i)],
-            ItemStacks::Multiple(is) => is.into_iter().map(|i| Ingredient::Item(i)).collect(),
+            ItemStacks::Multiple(is) => is.into_iter().map(Ingredient::Item).collect(),
         }
     }
 }
@@ -149,10 +283,15 @@ impl<'a> std::fmt::Display for ItemStacks<'a> {
 
 impl<'a> ItemStacks<'a> {
     #[inline(always)]
-    fn take_item_or_first(&self) -> &ItemStack<'a> {
+    fn take_item_or_first(&self) -> Result<&ItemStack<'a>> {
+        self.as_slice().first().context("配方没有产物")
+    }
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[ItemStack<'a>] {
         match self {
-            ItemStacks::Single(i) => i,
-            ItemStacks::Multiple(is) => unsafe { is.get_unchecked(0) },
+            ItemStacks::Single(i) => std::slice::from_ref(i),
+            ItemStacks::Multiple(is) => is,
         }
     }
 }
@@ -174,108 +313,127 @@ impl<'a> From<Cow<'a, str>> for Description<'a> {
 pub struct Shaped<'a> {
     pub pattern: Vec<Cow<'a, str>>,
     #[serde(borrow)]
-    pub key: FxHashMap<char, Key<'a>>,
+    pub key: BTreeMap<char, Key<'a>>,
     #[serde(borrow)]
     pub result: ItemStacks<'a>,
-}
-
-#[inline(always)]
-fn push_char(pattern: &mut Vec<String>, i: u8, ch: char, item: &ItemStack) -> Result<()> {
-    unsafe {
-        pattern.get_unchecked_mut(match i {
-            0..=2 => 0,
-            3..=5 => 1,
-            6..=8 => 2,
-            _ => bail!("物品 {item} 数量过多"),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assume_symmetry: Option<bool>,
+}
+
+/// 去掉四周的空行与空列, 并把各行补齐到相同宽度
+fn trim(rows: Vec<Vec<char>>) -> Vec<Cow<'static, str>> {
+    let used = |row: &Vec<char>| row.iter().any(|&c| c != ' ');
+    let (Some(top), Some(bottom)) = (rows.iter().position(used), rows.iter().rposition(used))
+    else {
+        return vec![];
+    };
+    let rows = &rows[top..=bottom];
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let column = |&c: &usize| rows.iter().any(|row| row.get(c).is_some_and(|&c| c != ' '));
+    let left = (0..width).find(column).unwrap_or(0);
+    let right = (0..width).rfind(column).unwrap_or(0);
+    rows.iter()
+        .map(|row| {
+            (left..=right)
+                .map(|c| row.get(c).copied().unwrap_or(' '))
+                .collect::<String>()
+                .into()
         })
-    }
-    .push(ch);
-    Ok(())
+        .collect()
 }
 
 impl<'a> Shaped<'a> {
     #[inline(always)]
-    fn create_pattern(item: ItemStack<'a>) -> Result<Vec<Cow<'a, str>>> {
-        let mut pattern = vec!["".to_owned(); 3];
-        for i in 0..item.count.unwrap_or(1) {
-            push_char(&mut pattern, i, '#', &item)?;
+    fn is_filled(&self, c: char) -> bool {
+        c != ' ' && self.key.contains_key(&c)
+    }
+
+    #[inline(always)]
+    pub fn count_key(&self, k: char) -> u32 {
+        if k == ' ' {
+            return 0;
         }
-        Ok(pattern.into_iter().map(Cow::from).collect())
+        let count: usize = self
+            .pattern
+            .iter()
+            .map(|s| s.chars().filter(|&c| c == k).count())
+            .sum();
+        u32::try_from(count).unwrap_or(u32::MAX)
     }
 
-    #[inline]
-    fn inverse(self) -> Result<Shaped<'a>> {
-        let mut vecs: Vec<ItemStack> = vec![];
-        let mut results: Vec<ItemStack> = self
-            .key
-            .into_iter()
-            .map(|(k, i)| {
-                let pair = i.take_item();
-                let count = self
+    /// 把产物逐格排进合成格: 格数与源配方相同时沿用源配方的形状, 否则每行三格依次排列
+    fn layout(cells: Vec<char>, source: Option<&Shaped>) -> Result<Vec<Cow<'a, str>>> {
+        ensure!(!cells.is_empty(), "配方没有产物");
+        ensure!(cells.len() <= 9, "产物数量 {} 超过合成格数", cells.len());
+        let rows = match source {
+            Some(source)
+                if source
+                    .pattern
+                    .iter()
+                    .flat_map(|row| row.chars())
+                    .filter(|&c| source.is_filled(c))
+                    .count()
+                    == cells.len() =>
+            {
+                let mut cells = cells.into_iter();
+                source
                     .pattern
                     .iter()
-                    .map(|s| s.chars().filter(|&c| c == k).count())
-                    .sum::<usize>() as u8;
-                match pair.item {
-                    "minecraft:bucket" if count > 1 => {
-                        for _ in 1..count {
-                            vecs.push(ItemStack {
-                                item: BUCKET,
-                                data: pair.data,
-                                count: None,
+                    .map(|row| {
+                        row.chars()
+                            .map(|c| match source.is_filled(c) {
+                                true => cells.next().unwrap_or(' '),
+                                false => ' ',
                             })
-                        }
-                        ItemStack {
-                            count: None,
-                            data: pair.data,
-                            item: pair.item,
-                        }
-                    }
-                    _ => ItemStack {
-                        count: Some(count),
-                        data: pair.data,
-                        item: pair.item,
-                    },
+                            .collect()
+                    })
+                    .collect()
+            }
+            _ => cells.chunks(3).map(<[char]>::to_vec).collect(),
+        };
+        Ok(trim(rows))
+    }
+
+    #[inline]
+    fn inverse(self) -> Result<Shaped<'a>> {
+        let results: Vec<ItemStack> = self
+            .key
+            .iter()
+            .map(|(&k, i)| {
+                let pair = i.take_item()?;
+                Ok(ItemStack {
+                    item: pair.item,
+                    data: pair.data,
+                    count: Some(self.count_key(k)),
                 }
+                .concrete()
+                .split())
             })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
             .collect();
-        results.extend(vecs.into_iter());
-        Ok(match self.result {
+        let (pattern, key) = match &self.result {
             ItemStacks::Multiple(items) => {
-                let mut pattern: Vec<String> = vec!["".to_owned(); 3];
-                let mut key: FxHashMap<char, Key> = FxHashMap::default();
-                for (mut i, item) in items.into_iter().enumerate() {
-                    let char = unsafe { CHARS.get_unchecked(i) };
-                    match item.item {
-                        "minecraft:bucket" if item.count.is_some() => {
-                            for c in 0..item.count.unwrap() {
-                                i += c as usize;
-                                push_char(&mut pattern, i as u8, *char, &item)?;
-                            }
-                        }
-                        _ => {
-                            push_char(&mut pattern, i as u8, *char, &item)?;
-                        }
-                    }
-                    key.insert(
-                        *char,
-                        Key::Item(ItemPair {
-                            item: item.item,
-                            data: item.data,
-                        }),
-                    );
-                }
-                Self {
-                    pattern: pattern.into_iter().map(Cow::from).collect(),
-                    key,
-                    result: ItemStacks::Multiple(results),
+                let mut cells = vec![];
+                let mut key: BTreeMap<char, Key> = BTreeMap::new();
+                for (i, item) in items.iter().enumerate() {
+                    let char = *CHARS.get(i).context("产物种类过多")?;
+                    cells.extend(std::iter::repeat_n(char, item.count.unwrap_or(1) as usize));
+                    key.insert(char, Key::Item((*item).into()));
                 }
+                (Self::layout(cells, Some(&self))?, key)
             }
-            ItemStacks::Single(item) => Shaped {
-                key: FxHashMap::from_iter([('#', Key::Item(item.into()))]),
-                pattern: Shaped::create_pattern(item)?,
-                result: ItemStacks::Multiple(results),
-            },
+            ItemStacks::Single(item) => (
+                Self::layout(vec!['#'; item.count.unwrap_or(1) as usize], Some(&self))?,
+                BTreeMap::from_iter([('#', Key::Item((*item).into()))]),
+            ),
+        };
+        Ok(Self {
+            pattern,
+            key,
+            result: ItemStacks::Multiple(results),
+            assume_symmetry: self.assume_symmetry,
         })
     }
 }
@@ -291,14 +449,39 @@ impl<'a> Shapeless<'a> {
     #[inline]
     fn inverse(self) -> Result<Shaped<'a>> {
         Ok(Shaped {
-            key: FxHashMap::from_iter([('#', Key::Item(self.result.into()))]),
-            pattern: Shaped::create_pattern(self.result)?,
+            key: BTreeMap::from_iter([('#', Key::Item(self.result.into()))]),
+            pattern: Shaped::layout(vec!['#'; self.result.count.unwrap_or(1) as usize], None)?,
             result: ItemStacks::Multiple(
                 self.ingredients
                     .into_iter()
-                    .map(|i| i.take_item())
+                    .map(|i| i.take_item().map(|i| i.concrete().split()))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .flatten()
                     .collect(),
             ),
+            assume_symmetry: None,
+        })
+    }
+
+    #[inline]
+    fn inverse_stonecutter(self) -> Result<Shapeless<'a>> {
+        let [Ingredient::Item(input)] = self.ingredients.as_slice() else {
+            bail!("切石机配方必须只有一个物品原料");
+        };
+        let (input_count, output_count) =
+            (input.count.unwrap_or(1), self.result.count.unwrap_or(1));
+        ensure!(input_count > 0 && output_count > 0, "物品数量不能为 0");
+        let gcd = gcd(input_count, output_count);
+        Ok(Self {
+            ingredients: vec![Ingredient::Item(ItemStack {
+                count: Some(output_count / gcd).filter(|&c| c > 1),
+                ..self.result
+            })],
+            result: ItemStack {
+                count: Some(input_count / gcd).filter(|&c| c > 1),
+                ..input.concrete()
+            },
         })
     }
 
@@ -319,15 +502,16 @@ pub enum Data<'a> {
     Shapeless(Shapeless<'a>),
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Unlock<'a> {
-    context: &'a str,
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Station {
+    CraftingTable,
+    Stonecutter,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct RecipeComponent<'a> {
     pub description: Description<'a>,
-    #[serde(skip_deserializing)]
+    #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(serialize_with = "serialize_unlock")]
     pub unlock: Option<Value>,
@@ -337,6 +521,38 @@ pub struct RecipeComponent<'a> {
     pub data: Data<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub priority: Option<i8>,
+    #[serde(borrow)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<Cow<'a, str>>,
+    /// 未建模的字段, 原样带到分解配方中
+    #[serde(flatten)]
+    #[serde(deserialize_with = "unknown_fields")]
+    pub extra: Map<String, Value>,
+    /// 分解时无法保留而丢弃的字段, 在运行结束时汇总报告
+    #[serde(skip)]
+    pub dropped: Vec<String>,
+}
+
+const KNOWN_FIELDS: [&str; 10] = [
+    "description",
+    "unlock",
+    "tags",
+    "pattern",
+    "key",
+    "result",
+    "assume_symmetry",
+    "ingredients",
+    "priority",
+    "group",
+];
+
+fn unknown_fields<'de, D>(deserializer: D) -> Result<Map<String, Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut fields = Map::deserialize(deserializer)?;
+    fields.retain(|k, _| !KNOWN_FIELDS.contains(&k.as_str()));
+    Ok(fields)
 }
 
 fn serialize_unlock<S>(unlock: &Option<Value>, serializer: S) -> Result<S::Ok, S::Error>
@@ -344,27 +560,19 @@ where
     S: Serializer,
 {
     match unlock {
-        Some(value) => {
-            if let Value::String(s) = value {
-                let mut unlock = serializer.serialize_struct("Unlock", 1)?;
-                unlock.serialize_field("context", s.as_str())?;
-                unlock.end()
-            } else {
-                return Err(serde::ser::Error::custom(format!(
-                    "Unlock必须为字符串: {}",
-                    value
-                )));
-            }
-        }
-        None => {
-            return Err(serde::ser::Error::custom("Unlock必须为字符串: None"));
+        Some(Value::String(s)) => {
+            let mut unlock = serializer.serialize_struct("Unlock", 1)?;
+            unlock.serialize_field("context", s.as_str())?;
+            unlock.end()
         }
+        Some(value) => value.serialize(serializer),
+        None => Err(serde::ser::Error::custom("Unlock不能为空")),
     }
 }
 
 #[derive(Deserialize)]
 pub struct Recipe<'a> {
-    #[serde(skip_deserializing)]
+    #[serde(default)]
     pub format_version: &'a str,
     #[serde(borrow)]
     #[serde(rename = "minecraft:recipe_shaped")]
@@ -379,18 +587,15 @@ impl<'a> Serialize for Recipe<'a> {
     {
         let mut recipe = serializer.serialize_struct("Recipe", 2)?;
         recipe.serialize_field("format_version", &self.format_version)?;
-        match &self.component {
-            Some(component) => {
-                recipe.serialize_field(
-                    match component.data {
-                        Data::Shaped(_) => "minecraft:recipe_shaped",
-                        Data::Shapeless(_) => "minecraft:recipe_shapeless",
-                    },
-                    &self.component,
-                )?;
-            }
-            None => {}
-        };
+        if let Some(component) = &self.component {
+            recipe.serialize_field(
+                match component.data {
+                    Data::Shaped(_) => "minecraft:recipe_shaped",
+                    Data::Shapeless(_) => "minecraft:recipe_shapeless",
+                },
+                &self.component,
+            )?;
+        }
         recipe.end()
     }
 }
@@ -399,84 +604,177 @@ impl<'a> From<RecipeComponent<'a>> for Recipe<'a> {
     #[inline(always)]
     fn from(value: RecipeComponent<'a>) -> Self {
         Self {
-            format_version: "1.21.10",
+            format_version: "",
             component: Some(value),
         }
     }
 }
 
 #[inline(always)]
-fn mq_decrafting_item(id: &str) -> String {
-    if id.starts_with("minecraft:") {
-        id.replace("minecraft:", "mq_decrafting_item:")
-    } else {
-        "mq_decrafting_item:".to_owned() + id
+pub fn local_name(id: &str) -> Cow<'_, str> {
+    match id.split_once(':') {
+        Some(("minecraft", name)) => Cow::Borrowed(name),
+        Some((namespace, name)) => Cow::Owned(format!("{namespace}__{name}")),
+        None => Cow::Borrowed(id),
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct Target<'a> {
+    pub recipe_id: &'a str,
+    pub item_id: &'a str,
+    pub crafting_tag: &'a str,
+    pub unlock: Unlock,
+}
+
 impl<'a> RecipeComponent<'a> {
     #[inline(always)]
-    pub fn new(id: &'a str, data: Data<'a>) -> Self {
-        Self {
-            description: Cow::Borrowed(id).into(),
-            unlock: Some("AlwaysUnlocked".into()),
-            tags: vec!["mq_decrafting_table"],
-            data,
-            priority: None,
+    pub fn is_deprecated(&self) -> bool {
+        self.tags.contains(&"deprecated")
+    }
+
+    #[inline(always)]
+    pub fn station(&self) -> Option<Station> {
+        if self.tags.contains(&"crafting_table") {
+            Some(Station::CraftingTable)
+        } else if self.tags.contains(&"stonecutter") {
+            Some(Station::Stonecutter)
+        } else {
+            None
         }
     }
 
     #[inline(always)]
-    pub fn is_deprecated(&self) -> bool {
-        self.tags.contains(&"deprecated")
+    fn result_stack(&self) -> Result<&ItemStack<'a>> {
+        Ok(match &self.data {
+            Data::Shaped(shaped) => shaped.result.take_item_or_first()?,
+            Data::Shapeless(shapeless) => &shapeless.result,
+        })
+    }
+
+    #[inline(always)]
+    pub fn result_item(&self) -> Result<&'a str> {
+        Ok(self.result_stack()?.item)
+    }
+
+    #[inline(always)]
+    /// 占位物品按配方 ID 命名, 同一物品的多个配方不会互相覆盖
+    pub fn placeholder_name(&self) -> String {
+        local_name(&self.description.identifier).into_owned()
+    }
+
+    pub fn result_items(&self) -> impl Iterator<Item = &str> {
+        match &self.data {
+            Data::Shaped(shaped) => shaped.result.as_slice(),
+            Data::Shapeless(shapeless) => std::slice::from_ref(&shapeless.result),
+        }
+        .iter()
+        .map(|i| i.item)
+    }
+
+    pub fn ingredient_items(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match &self.data {
+            Data::Shaped(shaped) => Box::new(shaped.key.values().map(|k| match k {
+                Key::Item(pair) => pair.item,
+                Key::Tag(tag) => tag.tag,
+            })),
+            Data::Shapeless(shapeless) => Box::new(shapeless.ingredients.iter().map(|i| match i {
+                Ingredient::Item(item) => item.item,
+                Ingredient::Tag(tag) => tag.tag,
+            })),
+        }
+    }
+
+    pub fn flow(&self) -> Flow {
+        let (inputs, outputs) = match &self.data {
+            Data::Shaped(shaped) => (
+                shaped
+                    .key
+                    .iter()
+                    .map(|(&k, key)| Stack::new(key.into(), shaped.count_key(k)))
+                    .collect(),
+                shaped.result.as_slice().iter().map(Stack::from).collect(),
+            ),
+            Data::Shapeless(shapeless) => (
+                shapeless.ingredients.iter().map(Stack::from).collect(),
+                vec![(&shapeless.result).into()],
+            ),
+        };
+        Flow {
+            id: self.description.identifier.to_string(),
+            inputs,
+            outputs,
+        }
+    }
+
+    pub fn features(&self) -> Vec<(&'static str, Version)> {
+        let mut features = vec![version::RECIPE];
+        if self.unlock.is_some() {
+            features.push(version::UNLOCK);
+        }
+        features
+    }
+
+    /// 分解配方的解锁条件, 按物品解锁时取被分解的物品 (即正向配方的第一个产物)
+    fn inverse_unlock(&mut self, unlock: Unlock) -> Result<Value> {
+        let source = self.unlock.take();
+        if let (Unlock::Always | Unlock::Result, Some(source)) = (unlock, &source) {
+            self.dropped.push(format!("unlock = {source}"));
+        }
+        Ok(match (unlock, source) {
+            (Unlock::Always, _) => "AlwaysUnlocked".into(),
+            (Unlock::Inherit, Some(unlock)) => unlock,
+            (Unlock::Result | Unlock::Inherit, _) => Value::Array(vec![serde_json::to_value(
+                ItemPair::from(*self.result_stack()?),
+            )?]),
+        })
     }
 
     #[inline]
     pub fn inverse(
         mut self,
-        result_recipe_id: &'a str,
-        result_item_id: &'a mut String,
+        target: Target<'a>,
     ) -> anyhow::Result<(Option<Recipe<'a>>, Option<LootTable<'a>>)> {
-        self.description.identifier = Cow::Owned(result_recipe_id.to_owned());
-        self.tags = vec!["mq_decrafting_table"];
-        self.unlock = Some("AlwaysUnlocked".into());
+        let Some(station) = self.station() else {
+            bail!("不支持的配方标签: {}", self.tags.join(", "));
+        };
+        self.description.identifier = Cow::Borrowed(target.recipe_id);
+        self.tags = vec![target.crafting_tag];
+        self.unlock = Some(self.inverse_unlock(target.unlock)?);
         Ok(match self.data {
+            Data::Shapeless(shapeless) if station == Station::Stonecutter => {
+                if shapeless.result.count.unwrap_or(1) > 9 {
+                    println!("物品数量过多: {}", &shapeless.result);
+                    return Ok((None, None));
+                }
+                self.data = Data::Shapeless(shapeless.inverse_stonecutter()?);
+                (Some(self.into()), None)
+            }
             Data::Shaped(shaped) => {
                 if match &shaped.result {
                     ItemStacks::Single(item) => item.count.unwrap_or(1) > 9,
                     ItemStacks::Multiple(items) => {
-                        items.iter().map(|item| item.count.unwrap_or(1)).sum::<u8>() > 9
+                        items
+                            .iter()
+                            .map(|item| u64::from(item.count.unwrap_or(1)))
+                            .sum::<u64>()
+                            > 9
                     }
                 } {
                     println!("物品数量过多: {}", &shaped.result);
                     return Ok((None, None));
                 }
-                if shaped.key.values().any(
-                    const {
-                        |v: &Key| {
-                            if let Key::Tag(_) = v {
-                                true
-                            } else {
-                                false
-                            }
-                        }
-                    },
-                ) {
-                    let itemstack: &ItemStack<'_> = shaped.result.take_item_or_first();
-                    result_item_id.push_str(&mq_decrafting_item(itemstack.item));
-                    (
-                        Some(
-                            RecipeComponent::new(
-                                result_recipe_id,
-                                Data::Shapeless(Shapeless::return_item(
-                                    shaped.result.clone().into(),
-                                    itemstack.crate_mq(result_item_id.as_str()),
-                                )),
-                            )
-                            .into(),
-                        ),
-                        Some(LootTable::from_shaped(shaped)?),
-                    )
+                if shaped.key.values().any(|v| matches!(v, Key::Tag(_))) {
+                    if let Some(assume_symmetry) = shaped.assume_symmetry {
+                        self.dropped
+                            .push(format!("assume_symmetry = {assume_symmetry}"));
+                    }
+                    let itemstack: &ItemStack<'_> = shaped.result.take_item_or_first()?;
+                    self.data = Data::Shapeless(Shapeless::return_item(
+                        shaped.result.clone().into(),
+                        itemstack.crate_mq(target.item_id),
+                    ));
+                    (Some(self.into()), Some(LootTable::from_shaped(shaped)?))
                 } else {
                     self.data = Data::Shaped(shaped.inverse()?);
                     (Some(self.into()), None)
@@ -486,32 +784,27 @@ impl<'a> RecipeComponent<'a> {
                 if shapeless.result.count.unwrap_or(1) > 9 {
                     return Ok((None, None));
                 }
-                if shapeless.ingredients.iter().any(
-                    const {
-                        |v: &Ingredient| {
-                            if let Ingredient::Tag(_) = v {
-                                true
-                            } else {
-                                false
-                            }
-                        }
-                    },
-                ) {
-                    result_item_id.push_str(&mq_decrafting_item(shapeless.result.item));
+                let slots: u64 = shapeless
+                    .ingredients
+                    .iter()
+                    .map(|i| match i {
+                        Ingredient::Item(item) => u64::from(item.count.unwrap_or(1)),
+                        Ingredient::Tag(_) => 1,
+                    })
+                    .sum();
+                ensure!(slots <= 9, "原料数量 {slots} 超过合成格数");
+                if shapeless
+                    .ingredients
+                    .iter()
+                    .any(|v| matches!(v, Ingredient::Tag(_)))
+                {
+                    self.data = Data::Shapeless(Shapeless::return_item(
+                        shapeless.result.into(),
+                        shapeless.result.crate_mq(target.item_id),
+                    ));
                     (
-                        Some(
-                            RecipeComponent::new(
-                                result_recipe_id,
-                                Data::Shapeless(Shapeless::return_item(
-                                    shapeless.result.into(),
-                                    shapeless.result.crate_mq(result_item_id.as_str()),
-                                )),
-                            )
-                            .into(),
-                        ),
-                        Some(LootTable::from_vec_ingredient(
-                            shapeless.ingredients.clone(),
-                        )?),
+                        Some(self.into()),
+                        Some(LootTable::from_vec_ingredient(shapeless.ingredients)?),
                     )
                 } else {
                     self.data = Data::Shaped(shapeless.inverse()?);
@@ -521,3 +814,134 @@ impl<'a> RecipeComponent<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(pattern: &[&str]) -> Vec<Vec<char>> {
+        pattern.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn item_syntaxes_round_trip() {
+        let ingredients: Vec<Ingredient> = serde_json::from_str(
+            r#"["minecraft:stick", "minecraft:wool:3", { "item": "minecraft:planks:2", "count": 2 },
+                { "item": "minecraft:dye", "data": 4 }, { "tag": "minecraft:logs" }]"#,
+        )
+        .unwrap();
+        let value = serde_json::to_value(&ingredients).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "item": "minecraft:stick" },
+                { "item": "minecraft:wool", "data": 3 },
+                { "item": "minecraft:planks", "data": 2, "count": 2 },
+                { "item": "minecraft:dye", "data": 4 },
+                { "tag": "minecraft:logs" }
+            ])
+        );
+        let s = value.to_string();
+        let again: Vec<Ingredient> = serde_json::from_str(&s).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), value);
+        assert!(
+            serde_json::from_str::<ItemStack>(r#"{ "item": "minecraft:wool:3", "data": 3 }"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn unknown_fields_survive_round_trip() {
+        let s = r#"{
+            "format_version": "1.20.10",
+            "minecraft:recipe_shapeless": {
+                "description": { "identifier": "addon:x" },
+                "tags": ["crafting_table"],
+                "unlock": [{ "item": "minecraft:stick" }],
+                "ingredients": ["minecraft:stick"],
+                "result": "addon:x",
+                "addon:sort": 3
+            }
+        }"#;
+        let recipe: Recipe = serde_json::from_str(s).unwrap();
+        let component = recipe.component.as_ref().unwrap();
+        assert_eq!(component.extra.keys().collect::<Vec<_>>(), ["addon:sort"]);
+        let value = serde_json::to_value(&recipe).unwrap();
+        let component = &value["minecraft:recipe_shapeless"];
+        assert_eq!(component["addon:sort"], 3);
+        assert_eq!(component["unlock"][0]["item"], "minecraft:stick");
+    }
+
+    #[test]
+    fn unlock_modes() {
+        let s = r#"{
+            "description": { "identifier": "minecraft:x" },
+            "tags": ["crafting_table"],
+            "unlock": { "context": "PlayerInWater" },
+            "ingredients": ["minecraft:stick"],
+            "result": "minecraft:wool:3"
+        }"#;
+        let unlock = |mode| {
+            let mut component: RecipeComponent = serde_json::from_str(s).unwrap();
+            (component.inverse_unlock(mode).unwrap(), component.dropped)
+        };
+        let dropped = [r#"unlock = {"context":"PlayerInWater"}"#];
+        assert_eq!(
+            unlock(Unlock::Always),
+            ("AlwaysUnlocked".into(), dropped.map(String::from).to_vec())
+        );
+        assert_eq!(
+            unlock(Unlock::Result),
+            (
+                serde_json::json!([{ "item": "minecraft:wool", "data": 3 }]),
+                dropped.map(String::from).to_vec()
+            )
+        );
+        assert_eq!(
+            unlock(Unlock::Inherit),
+            (serde_json::json!({ "context": "PlayerInWater" }), vec![])
+        );
+    }
+
+    #[test]
+    fn trim_drops_empty_rows_and_columns() {
+        assert_eq!(trim(rows(&["   ", " # ", "  #"])), ["# ", " #"]);
+        assert_eq!(trim(rows(&["#", "", " #"])), ["# ", "  ", " #"]);
+        assert!(trim(rows(&["   ", ""])).is_empty());
+    }
+
+    #[test]
+    fn unused_keys_are_not_returned() {
+        let shaped: Shaped = serde_json::from_str(
+            r###"{ "pattern": ["##"], "key": { "#": "x:a", "Y": "x:y" }, "result": "x:b" }"###,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(shaped.inverse().unwrap().result).unwrap(),
+            serde_json::json!([{ "item": "x:a", "count": 2 }])
+        );
+        let shaped: Shaped = serde_json::from_str(
+            r###"{ "pattern": ["##"], "key": { "#": { "tag": "minecraft:planks" }, "Y": "x:y" }, "result": "x:b" }"###,
+        )
+        .unwrap();
+        assert_eq!(LootTable::from_shaped(shaped).unwrap().outputs().len(), 1);
+    }
+
+    #[test]
+    fn layout_keeps_source_shape() {
+        let source: Shaped = serde_json::from_str(
+            r#"{ "pattern": [" X ", "X X"], "key": { "X": { "item": "x:a" } }, "result": { "item": "x:b" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Shaped::layout(vec!['A', 'B', 'B'], Some(&source)).unwrap(),
+            [" A ", "B B"]
+        );
+        assert_eq!(
+            Shaped::layout(vec!['#'; 4], Some(&source)).unwrap(),
+            ["###", "#  "]
+        );
+        assert!(Shaped::layout(vec![], None).is_err());
+        assert!(Shaped::layout(vec!['#'; 10], None).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    output::{to_pretty, Output},
+    samples::Samples,
+};
+use anyhow::{Context, Result};
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path};
+
+pub const LANGUAGES: [(&str, &str); 3] = [
+    ("en_US", "Decrafted {}"),
+    ("zh_CN", "分解的{}"),
+    ("zh_TW", "分解的{}"),
+];
+pub const DEFAULT_ICON: &str = "mq";
+
+fn parse_lang(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    s.lines().filter_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        let value = value.split_once("\t#").map_or(value, |(v, _)| v);
+        Some((key.trim(), value.trim_end()))
+    })
+}
+
+fn humanize(name: &str) -> String {
+    name.split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Default)]
+pub struct Vanilla {
+    textures: FxHashMap<String, String>,
+    langs: FxHashMap<&'static str, FxHashMap<String, String>>,
+}
+
+impl Vanilla {
+    pub fn load(samples: &mut Samples) -> Result<Self> {
+        let mut vanilla = Self::default();
+        if let Some(s) = samples
+            .read("resource_pack/textures/item_texture.json")
+            .context("无法读取原版 item_texture.json")?
+        {
+            let value: Value =
+                serde_json::from_str(&s).context("解析原版 item_texture.json 失败")?;
+            if let Some(data) = value["texture_data"].as_object() {
+                for (key, texture) in data {
+                    let path = match &texture["textures"] {
+                        Value::String(s) => Some(s.as_str()),
+                        Value::Array(a) => a.first().and_then(Value::as_str),
+                        _ => None,
+                    };
+                    if let Some(path) = path {
+                        vanilla.textures.insert(key.clone(), path.to_owned());
+                    }
+                }
+            }
+        }
+        for (lang, _) in LANGUAGES {
+            if let Some(s) = samples
+                .read(&format!("resource_pack/texts/{lang}.lang"))
+                .context("无法读取原版语言文件")?
+            {
+                vanilla.langs.insert(
+                    lang,
+                    parse_lang(&s)
+                        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                        .collect(),
+                );
+            }
+        }
+        for dir in ["items", "blocks"] {
+            for file in samples.list(&format!("resource_pack/textures/{dir}")) {
+                if let Some(name) = Path::new(&file).file_stem().and_then(|n| n.to_str()) {
+                    vanilla
+                        .textures
+                        .entry(name.to_owned())
+                        .or_insert_with(|| format!("textures/{dir}/{name}"));
+                }
+            }
+        }
+        Ok(vanilla)
+    }
+
+    fn icon(&self, item: &str) -> Option<&str> {
+        let name = item.strip_prefix("minecraft:")?;
+        self.textures.get(name).map(String::as_str)
+    }
+
+    fn name(&self, lang: &str, item: &str) -> String {
+        let name = item.split_once(':').map_or(item, |(_, n)| n);
+        [lang, "en_US"]
+            .into_iter()
+            .filter_map(|lang| self.langs.get(lang))
+            .find_map(|l| {
+                l.get(&format!("item.{name}.name"))
+                    .or_else(|| l.get(&format!("tile.{name}.name")))
+            })
+            .cloned()
+            .unwrap_or_else(|| humanize(name))
+    }
+}
+
+#[derive(Default)]
+pub struct Assets {
+    textures: BTreeMap<String, String>,
+    langs: BTreeMap<&'static str, BTreeMap<String, String>>,
+}
+
+impl Assets {
+    pub fn add_item(
+        &mut self,
+        vanilla: &Vanilla,
+        id: &str,
+        source: &str,
+        display_name: Option<&str>,
+    ) -> (String, String) {
+        let key = format!("item.{id}.name");
+        for (lang, template) in LANGUAGES {
+            self.langs.entry(lang).or_default().insert(
+                key.clone(),
+                match display_name {
+                    Some(name) => name.to_owned(),
+                    None => template.replace("{}", &vanilla.name(lang, source)),
+                },
+            );
+        }
+        let icon = match vanilla.icon(source) {
+            Some(path) => {
+                let alias = id.replace(':', ".");
+                self.textures.insert(alias.clone(), path.to_owned());
+                alias
+            }
+            None => DEFAULT_ICON.to_owned(),
+        };
+        (key, icon)
+    }
+
+    fn write_textures(&self, output: &mut Output, resource_pack: &Path, owned: &str) -> Result<()> {
+        let path = resource_pack.join("textures/item_texture.json");
+        let mut value: Value = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path).context("无法读取 item_texture.json")?)
+                .context("解析 item_texture.json 失败")?
+        } else {
+            serde_json::json!({
+                "resource_pack_name": resource_pack.file_name().and_then(|n| n.to_str()),
+                "texture_name": "atlas.items",
+                "texture_data": {},
+            })
+        };
+        let data = value["texture_data"]
+            .as_object_mut()
+            .context("item_texture.json 缺少 texture_data")?;
+        data.retain(|alias, _| !alias.starts_with(owned));
+        for (alias, texture) in &self.textures {
+            data.insert(alias.clone(), serde_json::json!({ "textures": texture }));
+        }
+        let buf = to_pretty(&value)?;
+        output
+            .merge(&path, buf)
+            .context("写入 item_texture.json 失败")
+    }
+
+    fn write_langs(&self, output: &mut Output, resource_pack: &Path, owned: &str) -> Result<()> {
+        let texts = resource_pack.join("texts");
+        let languages_path = texts.join("languages.json");
+        let mut languages: Vec<String> = if languages_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&languages_path)?)
+                .context("解析 languages.json 失败")?
+        } else {
+            vec![]
+        };
+        let len = languages.len();
+
+        let empty = BTreeMap::new();
+        for (lang, _) in LANGUAGES {
+            let entries = self.langs.get(lang).unwrap_or(&empty);
+            if !languages.iter().any(|l| l == lang) {
+                languages.push(lang.to_owned());
+            }
+            let path = texts.join(format!("{lang}.lang"));
+            let s = fs::read_to_string(&path).unwrap_or_default();
+            let mut lines: Vec<String> = s
+                .lines()
+                .filter(|line| {
+                    line.split_once('=')
+                        .is_none_or(|(key, _)| !key.trim().starts_with(owned))
+                })
+                .map(str::to_owned)
+                .collect();
+            lines.extend(entries.iter().map(|(k, v)| format!("{k}={v}")));
+            output
+                .merge(&path, lines.join("\n"))
+                .context("写入语言文件失败")?;
+        }
+
+        if languages.len() != len {
+            output
+                .merge(&languages_path, serde_json::to_string(&languages)?)
+                .context("写入 languages.json 失败")?;
+        }
+        Ok(())
+    }
+
+    pub fn write(
+        &self,
+        output: &mut Output,
+        resource_pack: &Path,
+        item_namespace: &str,
+    ) -> Result<()> {
+        self.write_textures(output, resource_pack, &format!("{item_namespace}."))?;
+        self.write_langs(output, resource_pack, &format!("item.{item_namespace}:"))
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+const RECIPES: &str = "behavior_pack/recipes/";
+
+pub fn recipe_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("读取文件夹失败: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            recipe_files(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "json") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub enum Samples {
+    Dir(PathBuf),
+    Zip {
+        archive: ZipArchive<BufReader<File>>,
+        root: String,
+    },
+}
+
+impl Samples {
+    pub fn open(path: &Path) -> Result<Self> {
+        if path.is_file() {
+            let archive = ZipArchive::new(BufReader::new(File::open(path)?))
+                .with_context(|| format!("无法打开压缩包: {}", path.display()))?;
+            let roots: BTreeSet<&str> = archive
+                .file_names()
+                .filter_map(|name| name.find(RECIPES).map(|i| &name[..i]))
+                .collect();
+            let root = match roots.into_iter().collect::<Vec<_>>().as_slice() {
+                [root] => root.to_string(),
+                [] => bail!("压缩包中没有 {RECIPES}"),
+                roots => bail!("压缩包中有多个 {RECIPES}: {}", roots.join(", ")),
+            };
+            return Ok(Self::Zip { archive, root });
+        }
+
+        if path.join(RECIPES).is_dir() {
+            return Ok(Self::Dir(path.to_owned()));
+        }
+        let mut roots = vec![];
+        for entry in
+            fs::read_dir(path).with_context(|| format!("读取文件夹失败: {}", path.display()))?
+        {
+            let entry = entry?.path();
+            if entry.join(RECIPES).is_dir() {
+                roots.push(entry);
+            }
+        }
+        roots.sort();
+        match roots.as_slice() {
+            [root] => Ok(Self::Dir(root.clone())),
+            [] => bail!("{} 中没有 {RECIPES}", path.display()),
+            roots => bail!(
+                "{} 中有多个 {RECIPES}: {}",
+                path.display(),
+                roots
+                    .iter()
+                    .map(|r| r.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            Samples::Dir(root) => root.display().to_string(),
+            Samples::Zip { root, .. } => format!("zip:{root}"),
+        }
+    }
+
+    pub fn recipes(&self) -> Result<Vec<String>> {
+        let mut recipes = match self {
+            Samples::Dir(root) => {
+                let mut files = vec![];
+                recipe_files(&root.join(RECIPES), &mut files)?;
+                files
+                    .into_iter()
+                    .filter_map(|f| {
+                        f.strip_prefix(root)
+                            .ok()
+                            .map(|f| f.to_string_lossy().replace('\\', "/"))
+                    })
+                    .collect()
+            }
+            Samples::Zip { archive, root } => archive
+                .file_names()
+                .filter_map(|name| name.strip_prefix(root.as_str()))
+                .filter(|name| name.starts_with(RECIPES) && name.ends_with(".json"))
+                .map(str::to_owned)
+                .collect::<Vec<_>>(),
+        };
+        recipes.sort();
+        Ok(recipes)
+    }
+
+    pub fn read(&mut self, path: &str) -> Result<Option<String>> {
+        match self {
+            Samples::Dir(root) => {
+                let path = root.join(path);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                Ok(Some(fs::read_to_string(path)?))
+            }
+            Samples::Zip { archive, root } => {
+                let Ok(mut file) = archive.by_name(&format!("{root}{path}")) else {
+                    return Ok(None);
+                };
+                let mut s = String::new();
+                file.read_to_string(&mut s)?;
+                Ok(Some(s))
+            }
+        }
+    }
+
+    pub fn list(&self, dir: &str) -> Vec<String> {
+        match self {
+            Samples::Dir(root) => fs::read_dir(root.join(dir))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|e| e.path().is_file())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .collect(),
+            Samples::Zip { archive, root } => {
+                let prefix = format!("{root}{dir}/");
+                archive
+                    .file_names()
+                    .filter_map(|name| name.strip_prefix(prefix.as_str()))
+                    .filter(|name| !name.is_empty() && !name.contains('/'))
+                    .map(str::to_owned)
+                    .collect()
+            }
+        }
+    }
+}
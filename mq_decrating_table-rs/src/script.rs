@@ -0,0 +1,71 @@
+use crate::{
+    exploit::{Material, Stack},
+    output::{to_pretty, Output},
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+const SCRIPT_TEMPLATE: &str = include_str!("decrafting.ts");
+
+#[derive(Serialize)]
+struct Return {
+    items: Vec<String>,
+    count: u32,
+}
+
+impl From<&Stack> for Return {
+    fn from(value: &Stack) -> Self {
+        Self {
+            items: match &value.material {
+                Material::Item(item) => vec![item.clone()],
+                Material::Tag(tag) => vec![format!("#{tag}")],
+                Material::AnyOf(items) | Material::Random(items) => items.clone(),
+            },
+            count: value.count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Entry {
+    loot: String,
+    recipe: String,
+    returns: Vec<Return>,
+}
+
+#[derive(Serialize)]
+pub struct ScriptManifest {
+    namespace: String,
+    items: BTreeMap<String, Entry>,
+}
+
+impl ScriptManifest {
+    pub fn new(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_owned(),
+            items: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, item: &str, loot: &str, recipe: &str, returns: &[Stack]) {
+        self.items.insert(
+            item.to_owned(),
+            Entry {
+                loot: loot.to_owned(),
+                recipe: recipe.to_owned(),
+                returns: returns.iter().map(Return::from).collect(),
+            },
+        );
+    }
+
+    pub fn write(&self, output: &mut Output, path: &Path) -> Result<()> {
+        let buf = to_pretty(self)?;
+        output
+            .write(
+                path,
+                SCRIPT_TEMPLATE.replace("$MANIFEST", &String::from_utf8(buf)?),
+            )
+            .context("写入脚本清单失败")
+    }
+}
@@ -0,0 +1,436 @@
+//! 离线合成模拟器: 按游戏规则匹配合成格并掷战利品表, 用于回环测试
+use crate::{loot_table::tag_items, recipe::ANY_DATA};
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Item {
+    pub id: String,
+    pub data: u64,
+}
+
+impl Item {
+    fn new(id: &str, data: u64) -> Self {
+        Self {
+            id: id.to_owned(),
+            data,
+        }
+    }
+}
+
+pub type Bag = BTreeMap<Item, u64>;
+pub type Grid = [[Option<Item>; 3]; 3];
+
+pub fn bag(grid: &Grid) -> Bag {
+    let mut bag = Bag::new();
+    for item in grid.iter().flatten().flatten() {
+        *bag.entry(item.clone()).or_default() += 1;
+    }
+    bag
+}
+
+fn count(value: &Value) -> u64 {
+    match value {
+        Value::Object(range) => range.get("min").and_then(Value::as_u64).unwrap_or(1),
+        value => value.as_u64().unwrap_or(1),
+    }
+}
+
+/// 物品 ID 与数据值, 兼容字符串简写与 `:data` 后缀
+fn item(value: &Value) -> (&str, Option<u64>) {
+    let id = value
+        .as_str()
+        .or_else(|| value["item"].as_str())
+        .unwrap_or_default();
+    let (id, suffix) = match id.rsplit_once(':') {
+        Some((item, data)) if item.contains(':') => match data.parse() {
+            Ok(data) => (item, Some(data)),
+            Err(_) => (id, None),
+        },
+        _ => (id, None),
+    };
+    (id, value.get("data").and_then(Value::as_u64).or(suffix))
+}
+
+fn results(value: &Value) -> Bag {
+    let stacks = match value {
+        Value::Array(stacks) => stacks.iter().collect(),
+        stack => vec![stack],
+    };
+    let mut bag = Bag::new();
+    for stack in stacks {
+        let (id, data) = item(stack);
+        let item = Item::new(id, data.unwrap_or(0));
+        *bag.entry(item).or_default() += stack.get("count").map_or(1, count);
+    }
+    bag
+}
+
+enum Matcher {
+    Item { id: String, data: Option<u64> },
+    Tag(String),
+}
+
+impl Matcher {
+    fn parse(value: &Value) -> Self {
+        match value.get("tag").and_then(Value::as_str) {
+            Some(tag) => Self::Tag(tag.to_owned()),
+            None => {
+                let (id, data) = item(value);
+                Self::Item {
+                    id: id.to_owned(),
+                    data,
+                }
+            }
+        }
+    }
+
+    fn accepts(&self, item: &Item) -> bool {
+        match self {
+            Self::Item { id, data } => {
+                *id == item.id && data.is_none_or(|d| d == item.data || d == ANY_DATA as u64)
+            }
+            Self::Tag(tag) => tag_items(tag).is_some_and(|items| items.contains(&item.id.as_str())),
+        }
+    }
+
+    fn example(&self) -> Item {
+        match self {
+            Self::Item { id, data } => {
+                Item::new(id, data.filter(|&d| d != ANY_DATA as u64).unwrap_or(0))
+            }
+            Self::Tag(tag) => Item::new(
+                tag_items(tag)
+                    .and_then(|items| items.first())
+                    .expect("不支持的 Tag"),
+                0,
+            ),
+        }
+    }
+}
+
+enum Shape {
+    Shaped {
+        rows: Vec<Vec<Option<Matcher>>>,
+        symmetric: bool,
+    },
+    Shapeless(Vec<Matcher>),
+}
+
+pub struct Recipe {
+    pub tags: Vec<String>,
+    shape: Shape,
+    results: Bag,
+}
+
+/// 裁掉四周的空行与空列
+fn trim<T>(rows: Vec<Vec<Option<T>>>) -> Vec<Vec<Option<T>>> {
+    let used = |row: &Vec<Option<T>>| row.iter().any(Option::is_some);
+    let (Some(top), Some(bottom)) = (rows.iter().position(used), rows.iter().rposition(used))
+    else {
+        return vec![];
+    };
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let column = |c: usize| {
+        rows.iter()
+            .any(|row| row.get(c).is_some_and(Option::is_some))
+    };
+    let left = (0..width).find(|&c| column(c)).unwrap_or(0);
+    let right = (0..width).rfind(|&c| column(c)).unwrap_or(0);
+    rows.into_iter()
+        .take(bottom + 1)
+        .skip(top)
+        .map(|row| {
+            let mut row: Vec<_> = row.into_iter().skip(left).take(right + 1 - left).collect();
+            row.resize_with(right + 1 - left, || None);
+            row
+        })
+        .collect()
+}
+
+impl Recipe {
+    pub fn parse(recipe: &Value) -> Option<Self> {
+        let (component, shaped) = match recipe.get("minecraft:recipe_shaped") {
+            Some(component) => (component, true),
+            None => (recipe.get("minecraft:recipe_shapeless")?, false),
+        };
+        let shape = if shaped {
+            let rows = component["pattern"]
+                .as_array()?
+                .iter()
+                .map(|row| {
+                    row.as_str()
+                        .unwrap_or_default()
+                        .chars()
+                        .map(|c| component["key"].get(c.to_string()).map(Matcher::parse))
+                        .collect()
+                })
+                .collect();
+            Shape::Shaped {
+                rows: trim(rows),
+                symmetric: component["assume_symmetry"].as_bool().unwrap_or(true),
+            }
+        } else {
+            Shape::Shapeless(
+                component["ingredients"]
+                    .as_array()?
+                    .iter()
+                    .flat_map(|i| (0..i.get("count").map_or(1, count)).map(|_| Matcher::parse(i)))
+                    .collect(),
+            )
+        };
+        Some(Self {
+            tags: component["tags"]
+                .as_array()?
+                .iter()
+                .filter_map(|t| t.as_str().map(str::to_owned))
+                .collect(),
+            shape,
+            results: results(&component["result"]),
+        })
+    }
+
+    /// 摆出一个满足该配方的合成格, Tag 取其第一个物品
+    pub fn place(&self) -> Grid {
+        let mut grid = Grid::default();
+        match &self.shape {
+            Shape::Shaped { rows, .. } => {
+                for (r, row) in rows.iter().enumerate() {
+                    for (c, cell) in row.iter().enumerate() {
+                        grid[r][c] = cell.as_ref().map(Matcher::example);
+                    }
+                }
+            }
+            Shape::Shapeless(ingredients) => {
+                for (i, ingredient) in ingredients.iter().enumerate() {
+                    grid[i / 3][i % 3] = Some(ingredient.example());
+                }
+            }
+        }
+        grid
+    }
+
+    fn matches(&self, grid: &Grid) -> bool {
+        match &self.shape {
+            Shape::Shaped { rows, symmetric } => {
+                let cells = trim(
+                    grid.iter()
+                        .map(|row| row.iter().map(Option::as_ref).collect())
+                        .collect(),
+                );
+                let fits = |mirror: bool| {
+                    rows.len() == cells.len()
+                        && rows.iter().zip(&cells).all(|(pattern, cells)| {
+                            pattern.len() == cells.len()
+                                && (0..cells.len()).all(|c| {
+                                    let p =
+                                        &pattern[if mirror { pattern.len() - 1 - c } else { c }];
+                                    match (p, cells[c]) {
+                                        (None, None) => true,
+                                        (Some(matcher), Some(item)) => matcher.accepts(item),
+                                        _ => false,
+                                    }
+                                })
+                        })
+                };
+                fits(false) || (*symmetric && fits(true))
+            }
+            Shape::Shapeless(ingredients) => {
+                let items: Vec<&Item> = grid.iter().flatten().flatten().collect();
+                fn assign(items: &[&Item], ingredients: &[&Matcher]) -> bool {
+                    let Some((item, rest)) = items.split_first() else {
+                        return ingredients.is_empty();
+                    };
+                    (0..ingredients.len()).any(|i| {
+                        ingredients[i].accepts(item) && {
+                            let mut left = ingredients.to_vec();
+                            left.remove(i);
+                            assign(rest, &left)
+                        }
+                    })
+                }
+                items.len() == ingredients.len()
+                    && assign(&items, &ingredients.iter().collect::<Vec<_>>())
+            }
+        }
+    }
+}
+
+/// 按固定选择掷战利品表: 每次抽取都取第 `choice` 个条目 (取模)
+pub fn roll(loot_table: &Value, choice: usize) -> Bag {
+    let mut bag = Bag::new();
+    for pool in loot_table["pools"].as_array().into_iter().flatten() {
+        let Some(entries) = pool["entries"].as_array().filter(|e| !e.is_empty()) else {
+            continue;
+        };
+        for _ in 0..count(&pool["rolls"]) {
+            let entry = &entries[choice % entries.len()];
+            let (mut count, mut data) = (1, 0);
+            for function in entry["functions"].as_array().into_iter().flatten() {
+                match function["function"].as_str() {
+                    Some("set_count") => count = self::count(&function["count"]),
+                    Some("set_data") => data = self::count(&function["data"]),
+                    _ => {}
+                }
+            }
+            let item = Item::new(entry["name"].as_str().unwrap_or_default(), data);
+            *bag.entry(item).or_default() += count;
+        }
+    }
+    bag
+}
+
+#[derive(Default)]
+pub struct Simulator {
+    recipes: Vec<Recipe>,
+    loot_tables: FxHashMap<String, Value>,
+}
+
+impl Simulator {
+    pub fn add_recipe(&mut self, recipe: &Value) {
+        self.recipes.extend(Recipe::parse(recipe));
+    }
+
+    pub fn add_loot_table(&mut self, item: &str, loot_table: Value) {
+        self.loot_tables.insert(item.to_owned(), loot_table);
+    }
+
+    pub fn craft(&self, station: &str, grid: &Grid) -> Option<Bag> {
+        self.recipes
+            .iter()
+            .find(|r| r.tags.iter().any(|t| t == station) && r.matches(grid))
+            .map(|r| r.results.clone())
+    }
+
+    /// 与脚本一致: 每个占位物品各掷一次对应的战利品表
+    pub fn open(&self, bag: Bag, choice: usize) -> Bag {
+        let mut opened = Bag::new();
+        for (item, count) in bag {
+            match self.loot_tables.get(&item.id) {
+                Some(loot_table) => {
+                    for _ in 0..count {
+                        for (item, count) in roll(loot_table, choice) {
+                            *opened.entry(item).or_default() += count;
+                        }
+                    }
+                }
+                None => *opened.entry(item).or_default() += count,
+            }
+        }
+        opened
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Unlock,
+        exploit::{Analyzer, ExploitKind, Material},
+        golden::fixtures,
+        recipe::{Recipe as Source, Target},
+    };
+
+    const DECRAFTING: &str = "mq_decrafting_table";
+
+    #[test]
+    fn round_trip() {
+        for (name, s) in fixtures() {
+            let forward_json: Value = serde_json::from_str(&s).unwrap();
+            let forward = Recipe::parse(&forward_json).unwrap();
+            let source: Source = serde_json::from_str(&s).unwrap();
+            let component = source.component.unwrap();
+            if component.is_deprecated() {
+                continue;
+            }
+
+            let mut analyzer = Analyzer::default();
+            analyzer.add_forward(component.flow());
+            let item_id = format!("test:{name}");
+            let (recipe, table) = component
+                .inverse(Target {
+                    recipe_id: "test:inverse",
+                    item_id: &item_id,
+                    crafting_tag: DECRAFTING,
+                    unlock: Unlock::Result,
+                })
+                .unwrap();
+            let recipe = recipe.unwrap();
+            analyzer.add_inverse(recipe.component.as_ref().unwrap().flow());
+
+            let mut simulator = Simulator::default();
+            simulator.add_recipe(&forward_json);
+            let inverse_json = serde_json::to_value(&recipe).unwrap();
+            simulator.add_recipe(&inverse_json);
+            if let Some(table) = table {
+                analyzer.add_loot(item_id.clone(), table.outputs());
+                simulator.add_loot_table(&item_id, serde_json::to_value(&table).unwrap());
+            }
+
+            let grid = forward.place();
+            let consumed = bag(&grid);
+            let crafted = simulator
+                .craft(&forward.tags[0], &grid)
+                .unwrap_or_else(|| panic!("{name}: 正向配方无法合成"));
+
+            let grid = Recipe::parse(&inverse_json).unwrap().place();
+            assert_eq!(bag(&grid), crafted, "{name}: 分解配方消耗的不是合成产物");
+            let decrafted = simulator
+                .craft(DECRAFTING, &grid)
+                .unwrap_or_else(|| panic!("{name}: 分解配方无法合成"));
+            let returned = simulator.open(decrafted, 0);
+            assert_eq!(returned, consumed, "{name}: 分解返回的不是合成消耗的原料");
+            // 随机战利品只会被报告为材料转换, 不应有其他漏洞
+            for exploit in analyzer.run() {
+                assert!(
+                    matches!(exploit.kind, ExploitKind::Conversion)
+                        && exploit
+                            .gained
+                            .iter()
+                            .all(|s| matches!(s.material, Material::Random(_))),
+                    "{name}: 报告了复制漏洞 {exploit}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shaped_matches_mirrored_and_offset() {
+        let recipe = Recipe::parse(&serde_json::json!({
+            "minecraft:recipe_shaped": {
+                "tags": ["crafting_table"],
+                "pattern": ["ab"],
+                "key": { "a": { "item": "x:a" }, "b": { "item": "x:b" } },
+                "result": { "item": "x:c" }
+            }
+        }))
+        .unwrap();
+        let mut grid = Grid::default();
+        grid[2][1] = Some(Item::new("x:b", 0));
+        grid[2][2] = Some(Item::new("x:a", 0));
+        assert!(recipe.matches(&grid));
+        grid[1][1] = Some(Item::new("x:a", 0));
+        assert!(!recipe.matches(&grid));
+    }
+
+    #[test]
+    fn asymmetric_shaped_rejects_mirror() {
+        let recipe = Recipe::parse(&serde_json::json!({
+            "minecraft:recipe_shaped": {
+                "tags": ["crafting_table"],
+                "assume_symmetry": false,
+                "pattern": ["ab"],
+                "key": { "a": "x:a:1", "b": { "item": "x:b" } },
+                "result": "x:c"
+            }
+        }))
+        .unwrap();
+        let mut grid = Grid::default();
+        grid[0][0] = Some(Item::new("x:a", 1));
+        grid[0][1] = Some(Item::new("x:b", 0));
+        assert!(recipe.matches(&grid));
+        grid[0].swap(0, 1);
+        assert!(!recipe.matches(&grid));
+    }
+}
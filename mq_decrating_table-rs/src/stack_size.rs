@@ -0,0 +1,86 @@
+//! 内置的最大堆叠数量表, 未列出的物品 (包括附加包物品) 按 64 处理
+pub const DEFAULT_STACK_SIZE: u32 = 64;
+
+const UNSTACKABLE_SUFFIXES: [&str; 14] = [
+    "_sword",
+    "_pickaxe",
+    "_axe",
+    "_shovel",
+    "_hoe",
+    "_helmet",
+    "_chestplate",
+    "_leggings",
+    "_boots",
+    "_horse_armor",
+    "_bucket",
+    "_boat",
+    "_raft",
+    "_minecart",
+];
+
+pub fn max_stack_size(item: &str) -> u32 {
+    let Some(name) = item.strip_prefix("minecraft:") else {
+        return DEFAULT_STACK_SIZE;
+    };
+    match name {
+        "bucket" | "ender_pearl" | "snowball" | "egg" | "blue_egg" | "brown_egg"
+        | "honey_bottle" | "armor_stand" | "banner" | "written_book" | "wind_charge" => 16,
+        "cake"
+        | "bed"
+        | "saddle"
+        | "writable_book"
+        | "enchanted_book"
+        | "potion"
+        | "splash_potion"
+        | "lingering_potion"
+        | "mushroom_stew"
+        | "rabbit_stew"
+        | "beetroot_soup"
+        | "suspicious_stew"
+        | "bow"
+        | "crossbow"
+        | "trident"
+        | "shield"
+        | "elytra"
+        | "fishing_rod"
+        | "carrot_on_a_stick"
+        | "warped_fungus_on_a_stick"
+        | "flint_and_steel"
+        | "shears"
+        | "brush"
+        | "mace"
+        | "spyglass"
+        | "totem_of_undying"
+        | "minecart"
+        | "goat_horn"
+        | "bundle"
+        | "shulker_box"
+        | "undyed_shulker_box" => 1,
+        _ if name.ends_with("_sign") || name.ends_with("_banner") => 16,
+        _ if name.starts_with("music_disc_")
+            || name.ends_with("_bed")
+            || name.ends_with("_shulker_box")
+            || name.ends_with("_bundle")
+            || UNSTACKABLE_SUFFIXES.iter().any(|s| name.ends_with(s)) =>
+        {
+            1
+        }
+        _ => DEFAULT_STACK_SIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_sizes() {
+        assert_eq!(max_stack_size("minecraft:stone"), 64);
+        assert_eq!(max_stack_size("minecraft:ender_pearl"), 16);
+        assert_eq!(max_stack_size("minecraft:bucket"), 16);
+        assert_eq!(max_stack_size("minecraft:milk_bucket"), 1);
+        assert_eq!(max_stack_size("minecraft:diamond_pickaxe"), 1);
+        assert_eq!(max_stack_size("minecraft:oak_hanging_sign"), 16);
+        assert_eq!(max_stack_size("addon:gem"), DEFAULT_STACK_SIZE);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::stack_size::max_stack_size;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+const RECIPE_SCHEMA: &str = include_str!("schemas/recipe.json");
+const LOOT_TABLE_SCHEMA: &str = include_str!("schemas/loot_table.json");
+const ITEM_SCHEMA: &str = include_str!("schemas/item.json");
+const GRID_SIZE: u64 = 9;
+
+fn schema(s: &str) -> Result<jsonschema::Validator> {
+    let schema = serde_json::from_str(s).context("解析内置 schema 失败")?;
+    jsonschema::validator_for(&schema).map_err(|e| anyhow::anyhow!("内置 schema 无效: {e}"))
+}
+
+fn count(value: &Value) -> u64 {
+    match value {
+        Value::Object(range) => range.get("max").and_then(Value::as_u64).unwrap_or(0),
+        value => value.as_u64().unwrap_or(1),
+    }
+}
+
+fn check_results(results: &Value, problems: &mut Vec<String>) {
+    let results = match results {
+        Value::Array(results) => results.iter().collect(),
+        result => vec![result],
+    };
+    for result in results {
+        let count = result.get("count").map_or(1, count);
+        let limit = max_stack_size(result["item"].as_str().unwrap_or_default());
+        if count > limit.into() {
+            problems.push(format!(
+                "产物 {} 的数量 {count} 超过堆叠上限 {limit}",
+                result["item"]
+            ));
+        }
+    }
+}
+
+fn check_shaped(shaped: &Value, problems: &mut Vec<String>) {
+    let pattern: Vec<&str> = shaped["pattern"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    // 中间的空行是合法的间隔, 只有首尾的空行会多占合成格
+    if [pattern.first(), pattern.last()]
+        .into_iter()
+        .flatten()
+        .any(|row| row.trim().is_empty())
+    {
+        problems.push("pattern 首尾含有空行".to_owned());
+    }
+    if let Some(width) = pattern.first().map(|row| row.chars().count()) {
+        if pattern.iter().any(|row| row.chars().count() != width) {
+            problems.push("pattern 各行宽度不一致".to_owned());
+        }
+    }
+    let key = shaped["key"].as_object();
+    for c in pattern
+        .iter()
+        .flat_map(|row| row.chars())
+        .filter(|&c| c != ' ')
+    {
+        if !key.is_some_and(|key| key.contains_key(&c.to_string())) {
+            problems.push(format!("pattern 中的 '{c}' 没有对应的 key"));
+        }
+    }
+    for k in key.into_iter().flat_map(|key| key.keys()) {
+        if !pattern.iter().any(|row| row.contains(k.as_str())) {
+            problems.push(format!("key '{k}' 未在 pattern 中使用"));
+        }
+    }
+    check_results(&shaped["result"], problems);
+}
+
+fn check_shapeless(shapeless: &Value, problems: &mut Vec<String>) {
+    let slots: u64 = shapeless["ingredients"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|ingredient| ingredient.get("count").map_or(1, count))
+        .sum();
+    if slots > GRID_SIZE {
+        problems.push(format!("原料数量 {slots} 超过合成格数 {GRID_SIZE}"));
+    }
+    check_results(&shapeless["result"], problems);
+}
+
+fn check_loot_table(loot_table: &Value, problems: &mut Vec<String>) {
+    let entries = loot_table["pools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pool| pool["entries"].as_array())
+        .flatten();
+    for entry in entries {
+        let functions = entry["functions"].as_array().into_iter().flatten();
+        for function in functions.filter(|f| f["function"] == "set_count") {
+            let count = count(&function["count"]);
+            let limit = max_stack_size(entry["name"].as_str().unwrap_or_default());
+            if count > limit.into() {
+                problems.push(format!(
+                    "{} 的数量 {count} 超过堆叠上限 {limit}",
+                    entry["name"]
+                ));
+            }
+        }
+    }
+}
+
+fn report(
+    violations: &mut Vec<String>,
+    path: &Path,
+    schema: &jsonschema::Validator,
+    value: &Value,
+    problems: Vec<String>,
+) {
+    let errors = schema
+        .iter_errors(value)
+        .map(|e| format!("{}: {e}", e.instance_path()));
+    for problem in errors.chain(problems) {
+        violations.push(format!("{}: {problem}", path.display()));
+    }
+}
+
+pub struct Validator {
+    recipe: jsonschema::Validator,
+    loot_table: jsonschema::Validator,
+    item: jsonschema::Validator,
+    pub violations: Vec<String>,
+}
+
+impl Validator {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            recipe: schema(RECIPE_SCHEMA)?,
+            loot_table: schema(LOOT_TABLE_SCHEMA)?,
+            item: schema(ITEM_SCHEMA)?,
+            violations: vec![],
+        })
+    }
+
+    pub fn recipe(&mut self, path: &Path, recipe: &Value) {
+        let mut problems = vec![];
+        if let Some(shaped) = recipe.get("minecraft:recipe_shaped") {
+            check_shaped(shaped, &mut problems);
+        }
+        if let Some(shapeless) = recipe.get("minecraft:recipe_shapeless") {
+            check_shapeless(shapeless, &mut problems);
+        }
+        report(&mut self.violations, path, &self.recipe, recipe, problems);
+    }
+
+    pub fn loot_table(&mut self, path: &Path, loot_table: &Value) {
+        let mut problems = vec![];
+        check_loot_table(loot_table, &mut problems);
+        report(
+            &mut self.violations,
+            path,
+            &self.loot_table,
+            loot_table,
+            problems,
+        );
+    }
+
+    pub fn item(&mut self, path: &Path, item: &Value) {
+        report(&mut self.violations, path, &self.item, item, vec![]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn problems(pattern: &[&str]) -> Vec<String> {
+        let mut problems = vec![];
+        let shaped = json!({ "pattern": pattern, "key": { "#": "x:a" }, "result": "x:b" });
+        check_shaped(&shaped, &mut problems);
+        problems
+    }
+
+    #[test]
+    fn only_outer_blank_rows_are_rejected() {
+        assert!(problems(&["# ", "  ", " #"]).is_empty());
+        assert_eq!(problems(&["  ", "# "]), ["pattern 首尾含有空行"]);
+        assert_eq!(problems(&["# ", "  "]), ["pattern 首尾含有空行"]);
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::{ensure, Context, Result};
+
+pub type Version = [u32; 3];
+
+pub const RECIPE: (&str, Version) = ("配方", [1, 12, 0]);
+pub const UNLOCK: (&str, Version) = ("配方解锁条件 unlock", [1, 20, 10]);
+pub const ITEM: (&str, Version) = ("物品图标与显示名称组件", [1, 20, 80]);
+
+pub fn parse(s: &str) -> Option<Version> {
+    let mut version = [0; 3];
+    for (i, part) in s.split('.').enumerate() {
+        *version.get_mut(i)? = part.parse().ok()?;
+    }
+    Some(version)
+}
+
+fn display([major, minor, patch]: Version) -> String {
+    format!("{major}.{minor}.{patch}")
+}
+
+/// 未配置目标版本时取源格式版本与各特性所需版本中的最大值
+pub fn resolve(source: &str, features: &[(&str, Version)], target: Option<&str>) -> Result<String> {
+    if let Some(target) = target {
+        let version = parse(target).with_context(|| format!("无法解析目标格式版本: {target}"))?;
+        for &(feature, required) in features {
+            ensure!(
+                version >= required,
+                "目标格式版本 {target} 无法表示{feature}, 至少需要 {}",
+                display(required)
+            );
+        }
+        return Ok(target.to_owned());
+    }
+    let required = features.iter().map(|&(_, v)| v).max().unwrap_or_default();
+    if source.is_empty() {
+        return Ok(display(required));
+    }
+    let version = parse(source).with_context(|| format!("无法解析源格式版本: {source}"))?;
+    Ok(if version >= required {
+        source.to_owned()
+    } else {
+        display(required)
+    })
+}
@@ -0,0 +1,17 @@
+// 由 mq_decrating_table-rs 生成, 请勿手动修改
+
+export interface DecraftingReturn {
+	items: string[];
+	count: number;
+}
+
+export interface DecraftingEntry {
+	loot: string;
+	recipe: string;
+	returns: DecraftingReturn[];
+}
+
+export const manifest: { namespace: string; items: Record<string, DecraftingEntry> } = {
+	"namespace": "mq_decrafting_item",
+	"items": {}
+};
@@ -1,4 +1,5 @@
 import { Container, EntityInventoryComponent, ItemStack, Player, PlayerCursorInventoryComponent, system, world } from '@minecraft/server';
+import { manifest } from './decrafting';
 
 declare module '@minecraft/server' {
 	interface Player {
@@ -11,9 +12,14 @@ declare module '@minecraft/server' {
 }
 
 Player.prototype.Decraft = function (item: ItemStack | undefined) {
-	if (item?.typeId.startsWith('mq_decrafting_item:')) {
-		if (item.typeId !== 'mq_decrafting_item:mq') {
-			const cmd = `loot spawn ~~~ loot "decrafting/${item.typeId.slice(19)}"`;
+	if (item?.typeId.startsWith(manifest.namespace + ':')) {
+		if (item.typeId !== manifest.namespace + ':mq') {
+			const entry = manifest.items[item.typeId];
+			if (entry === undefined) {
+				this.sendMessage('未知的分解物品: ' + item.typeId);
+				return item;
+			}
+			const cmd = `loot spawn ~~~ loot "${entry.loot}"`;
 			while (item.amount > 1) {
 				item.amount--;
 				if (this.runCommand(cmd).successCount === 0) {